@@ -0,0 +1,114 @@
+//! Compares the quadtree and the spatial hash on the mario level.
+//!
+//! Run with `cargo bench --bench broadphase`.
+
+use mold2d::level::{GRID_SIZE, Level};
+use mold2d::{ActorData, ActorIndex, Broadphase, CollisionLayers, QueryFilter, TileShape};
+use sdl2::rect::Rect;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const LEVEL_PATH: &str = "examples/mario/levels/level1.txt";
+const FRAMES: i32 = 1000;
+/// The tokens that the mario example spawns as actors instead of tiles
+const ACTOR_TOKENS: &str = "PCK";
+
+/// Creates the data for every token in the level, with the player,
+/// the enemies and the coins as the actors that move every frame
+fn level_actors(level: &Level) -> (Vec<ActorData<char>>, Vec<ActorIndex>) {
+    let mut actors = Vec::new();
+    let mut moving = Vec::new();
+    for (id, (token, position)) in level.main().tokens().enumerate() {
+        let index = ActorIndex { id, generation: 0 };
+        if ACTOR_TOKENS.contains(token.0) {
+            moving.push(index);
+        }
+        actors.push(ActorData {
+            index,
+            state: 0,
+            damage: 0,
+            collision_filter: 0b1111,
+            resolves_collisions: true,
+            rect: Rect::new(position.0, position.1, GRID_SIZE as u32, GRID_SIZE as u32),
+            bounding_box: None,
+            shape: TileShape::Solid,
+            layers: CollisionLayers::default(),
+            trigger: false,
+            actor_type: token.0,
+        });
+    }
+    (actors, moving)
+}
+
+/// Runs the same frames the game does against a spatial index and
+/// prints the time spent inserting, moving and querying the actors
+fn run(broadphase: Broadphase, bounds: Rect, actors: &[ActorData<char>], moving: &[ActorIndex]) {
+    let mut index = broadphase.create(bounds);
+    let filter = QueryFilter::new();
+
+    let start = Instant::now();
+    for actor in actors {
+        index.insert(*actor);
+    }
+    let insert = start.elapsed();
+
+    let (mut update, mut retrieve, mut nearest) = (Duration::ZERO, Duration::ZERO, Duration::ZERO);
+    for frame in 0..FRAMES {
+        let start = Instant::now();
+        for &actor in moving {
+            let mut rect = index.get(actor).unwrap().rect;
+            let x = (rect.x() + 4 * (frame % 2 * 2 - 1)).rem_euclid(bounds.width() as i32);
+            rect.set_x(x);
+            index.update_position(actor, rect);
+        }
+        update += start.elapsed();
+
+        let start = Instant::now();
+        for &actor in moving {
+            let rect = index.get(actor).unwrap().rect;
+            black_box(index.retrieve(&rect));
+            black_box(index.query_rect(&rect, &filter));
+        }
+        retrieve += start.elapsed();
+
+        let start = Instant::now();
+        for &actor in moving {
+            let center = index.get(actor).unwrap().rect.center();
+            let point = (f64::from(center.x()), f64::from(center.y()));
+            black_box(index.nearest(point, 4, &filter));
+        }
+        nearest += start.elapsed();
+    }
+
+    let per_frame = |total: Duration| total / FRAMES as u32;
+    println!(
+        "{:<20} insert {:>10?}  update {:>10?}  retrieve {:>10?}  nearest {:>10?}  (per frame)",
+        format!("{:?}", broadphase),
+        insert,
+        per_frame(update),
+        per_frame(retrieve),
+        per_frame(nearest),
+    );
+}
+
+fn main() {
+    let level = Level::load(LEVEL_PATH).expect("could not load the mario level");
+    let (width, height) = level.main().dimensions();
+    let bounds = Rect::new(0, 0, width as u32, height as u32);
+    let (actors, moving) = level_actors(&level);
+    println!(
+        "{} actors, {} moving, {} frames",
+        actors.len(),
+        moving.len(),
+        FRAMES
+    );
+
+    for broadphase in [
+        Broadphase::Quadtree,
+        Broadphase::SpatialHash(GRID_SIZE),
+        Broadphase::SpatialHash(GRID_SIZE * 2),
+        Broadphase::SpatialHash(GRID_SIZE * 4),
+    ] {
+        run(broadphase, bounds, &actors, &moving);
+    }
+}
@@ -1,11 +1,16 @@
-use crate::actors::block::{GroundBlockMid, GroundBlockTop, StartBlock, StoneBlock};
+use crate::actors::block::{
+    GroundBlockMid, GroundBlockTop, PlatformBlock, SlopeDownBlock, SlopeUpBlock, StartBlock,
+    StoneBlock,
+};
 use crate::actors::coin::Coin;
 use crate::actors::koopa::Koopa;
 use crate::actors::player::Player;
 use mold2d;
+use mold2d::autotile;
 use mold2d::{
-    ActorIndex, ActorManager, ActorPosition, ActorToken, CollisionSide, Context, MessageHandler,
-    PositionChange, Viewport,
+    ActorIndex, ActorManager, ActorPosition, ActorToken, AutotileRules, CollisionLayers,
+    CollisionPair, CollisionSide, Context, MessageHandler, Neighborhood, PairEvent, PositionChange,
+    Sprite, Spritesheet, SpritesheetConfig, TileShape, TileType, Tilemap, TriggerEvent, Viewport,
 };
 use sdl2::render::Canvas;
 use sdl2::video::Window;
@@ -20,11 +25,20 @@ pub enum ActorAction {
     /// A message sent by an acter when it collides
     /// into another actor
     Collision(ActorType, CollisionSide),
+    /// A message sent to a trigger and the actor that
+    /// entered, stayed in or exited the trigger
+    Trigger(ActorType, TriggerEvent),
     /// Ask message sent by an actor to ask another
     /// actor if it can bounce on it
     CanBounce,
     /// Response from asked actor to question
     Bounce(bool),
+    /// A message sent to an actor with how far up
+    /// it can grow before it hits a block
+    Headroom(i32),
+    /// A message sent to an enemy with the sides where it
+    /// would walk off of a ledge or into a wall
+    Ledges { left: bool, right: bool },
 }
 
 /// Actor messages
@@ -53,6 +67,14 @@ pub enum ActorType {
     Enemy,
 }
 
+/// Collision layers
+pub mod layers {
+    pub const PLAYER: u32 = 1 << 0;
+    pub const ENEMY: u32 = 1 << 1;
+    pub const ITEM: u32 = 1 << 2;
+    pub const BLOCK: u32 = 1 << 3;
+}
+
 pub type Actor = dyn mold2d::Actor<Type = ActorType, Message = ActorMessage>;
 pub type ActorData = mold2d::ActorData<ActorType>;
 
@@ -70,13 +92,92 @@ pub fn actor_from_token(
         'C' => Box::new(Coin::new(index, position, canvas, 20.)),
         'K' => Box::new(Koopa::new(index, position, canvas, 30.)),
         'S' => Box::new(StartBlock::new(index, position, canvas, 1.)),
-        '=' => Box::new(GroundBlockTop::new(index, position, canvas, 1.)),
+        '=' | 'G' => Box::new(GroundBlockTop::new(index, position, canvas, 1.)),
         '-' => Box::new(GroundBlockMid::new(index, position, canvas, 1.)),
         '_' => Box::new(StoneBlock::new(index, position, canvas, 1.)),
+        '~' => Box::new(PlatformBlock::new(index, position, canvas, 1.)),
+        '/' => Box::new(SlopeUpBlock::new(index, position, canvas, 1.)),
+        '\\' => Box::new(SlopeDownBlock::new(index, position, canvas, 1.)),
         _ => panic!("Actor not implemented for token!"),
     }
 }
 
+/// Loads the spritesheet with the block and decoration sprites
+fn tiles_spritesheet(canvas: &mut Canvas<Window>) -> Spritesheet {
+    Spritesheet::new(
+        SpritesheetConfig {
+            width: 80,
+            height: 80,
+            sprites_in_row: 7,
+            path: "assets/tiles.png",
+        },
+        canvas,
+    )
+}
+
+/// Returns the sprite for a token in a background or foreground layer
+pub fn sprite_for_token(
+    ActorToken(token): ActorToken,
+    canvas: &mut Canvas<Window>,
+) -> Option<Sprite> {
+    let index = match token {
+        '|' => 37,
+        _ => return None,
+    };
+    tiles_spritesheet(canvas).range(index, index + 1).pop()
+}
+
+/// Returns the tile type for block tokens that are part of the tilemap
+/// instead of being separate actors
+pub fn tile_for_token(
+    ActorToken(token): ActorToken,
+    canvas: &mut Canvas<Window>,
+) -> Option<TileType<ActorType>> {
+    let (index, collision_filter, shape) = match token {
+        'S' => (0, 0b1111, TileShape::Solid),
+        '=' | 'G' => (14, 0b1111, TileShape::Solid),
+        '-' => (21, 0b1110, TileShape::Solid),
+        '_' => (7, 0b1111, TileShape::Solid),
+        '~' => (10, 0b0001, TileShape::OneWay),
+        '/' => (
+            14,
+            0b0001,
+            TileShape::Slope {
+                left: 0.0,
+                right: 1.0,
+            },
+        ),
+        '\\' => (
+            14,
+            0b0001,
+            TileShape::Slope {
+                left: 1.0,
+                right: 0.0,
+            },
+        ),
+        _ => return None,
+    };
+
+    Some(TileType {
+        sprite: tiles_spritesheet(canvas).range(index, index + 1).pop(),
+        animation: None,
+        collision_filter,
+        shape,
+        layers: CollisionLayers::new(layers::BLOCK, 0),
+        actor_type: ActorType::Block,
+    })
+}
+
+/// Picks the top or middle ground sprite for the ground ('G') tiles
+/// depending on whether there is ground above them
+pub fn autotile_ground(tilemap: &mut Tilemap<ActorType>, canvas: &mut Canvas<Window>) {
+    if let Some(ground) = tilemap.token_tile(ActorToken('G')) {
+        let mut rules = AutotileRules::new(Neighborhood::FourWay, 14);
+        rules.add_rule(autotile::NORTH, autotile::NORTH, 21);
+        tilemap.autotile(ground, &rules, &tiles_spritesheet(canvas));
+    }
+}
+
 #[inline]
 pub fn handle_message(
     _curr_actor_id: ActorIndex,
@@ -111,38 +212,47 @@ pub fn handle_message(
     }
 }
 
-/// Moves actor away from collided actor.
+/// Moves an actor by a position change for the world step.
 #[inline]
-pub fn resolve_collision(actor: &mut Actor, other: &ActorData, direction: CollisionSide) {
-    let data = actor.data();
+pub fn move_actor(actor: &mut Actor, change: PositionChange) {
     let invalid_index = ActorIndex {
         id: 0,
         generation: 0,
     };
-    if data.resolves_collisions {
-        while actor.collides_with(other) == Some(direction) {
-            let change = match direction {
-                CollisionSide::Top => PositionChange::new().down(1),
-                CollisionSide::Bottom => PositionChange::new().up(1),
-                CollisionSide::Left => PositionChange::new().right(1),
-                CollisionSide::Right => PositionChange::new().left(1),
-            };
-
-            actor.handle_message(&ActorMessage::ActorAction {
-                send_id: invalid_index,
-                recv_id: invalid_index,
-                action: ActorAction::ChangePosition(change),
-            });
-        }
+    actor.handle_message(&ActorMessage::ActorAction {
+        send_id: invalid_index,
+        recv_id: invalid_index,
+        action: ActorAction::ChangePosition(change),
+    });
+}
 
-        if direction == CollisionSide::Bottom {
-            let down_change = PositionChange::new().down(1);
-            actor.handle_message(&ActorMessage::ActorAction {
-                send_id: invalid_index,
-                recv_id: invalid_index,
-                action: ActorAction::ChangePosition(down_change),
-            });
-        }
+/// Sends the collision or trigger messages for a pair found by the world step.
+#[inline]
+pub fn handle_pair(
+    pair: &CollisionPair<ActorType>,
+    actors: &mut ActorManager<Actor>,
+    viewport: &mut Viewport,
+    context: &mut Context,
+) {
+    match pair.event {
+        PairEvent::Collision(side) => handle_collision(
+            &pair.actor,
+            &pair.other,
+            side,
+            &handle_message,
+            actors,
+            viewport,
+            context,
+        ),
+        PairEvent::Trigger(event) => handle_trigger(
+            &pair.actor,
+            &pair.other,
+            event,
+            &handle_message,
+            actors,
+            viewport,
+            context,
+        ),
     }
 }
 
@@ -176,3 +286,29 @@ pub fn handle_collision(
         (handler)(actor.index, actors, viewport, context, &other_msg);
     }
 }
+
+/// Sends trigger event messages to the trigger and the actor in it.
+#[inline]
+pub fn handle_trigger(
+    trigger: &ActorData,
+    actor: &ActorData,
+    event: TriggerEvent,
+    handler: &MessageHandler<Actor>,
+    actors: &mut ActorManager<Actor>,
+    viewport: &mut Viewport,
+    context: &mut Context,
+) {
+    let trigger_msg = ActorMessage::ActorAction {
+        send_id: actor.index,
+        recv_id: trigger.index,
+        action: ActorAction::Trigger(actor.actor_type, event),
+    };
+    let actor_msg = ActorMessage::ActorAction {
+        send_id: trigger.index,
+        recv_id: actor.index,
+        action: ActorAction::Trigger(trigger.actor_type, event),
+    };
+
+    (handler)(actor.index, actors, viewport, context, &trigger_msg);
+    (handler)(actor.index, actors, viewport, context, &actor_msg);
+}
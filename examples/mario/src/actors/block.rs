@@ -1,5 +1,11 @@
-use crate::actions::{ActorMessage, ActorType};
-use mold2d::block;
+use crate::actions::{ActorMessage, ActorType, layers};
+use mold2d::{CollisionLayers, TileShape, block};
+
+/// Blocks don't move so they never check for collisions themselves
+const BLOCK_LAYERS: CollisionLayers = CollisionLayers {
+    layer: layers::BLOCK,
+    mask: 0,
+};
 
 block! {
     actor_type: ActorType,
@@ -13,7 +19,8 @@ block! {
             height: 80,
             sprites_in_row: 7,
             size: 40,
-            collision_filter: 0b1111
+            collision_filter: 0b1111,
+            layers: BLOCK_LAYERS
         }
 
         block {
@@ -24,7 +31,8 @@ block! {
             height: 80,
             sprites_in_row: 7,
             size: 40,
-            collision_filter: 0b1111
+            collision_filter: 0b1111,
+            layers: BLOCK_LAYERS
         }
 
         block {
@@ -35,7 +43,8 @@ block! {
             height: 80,
             sprites_in_row: 7,
             size: 40,
-            collision_filter: 0b1110
+            collision_filter: 0b1110,
+            layers: BLOCK_LAYERS
         }
 
         block {
@@ -46,7 +55,47 @@ block! {
             height: 80,
             sprites_in_row: 7,
             size: 40,
-            collision_filter: 0b1111
+            collision_filter: 0b1111,
+            layers: BLOCK_LAYERS
+        }
+
+        block {
+            name: PlatformBlock,
+            path: "assets/tiles.png",
+            index: 10,
+            width: 80,
+            height: 80,
+            sprites_in_row: 7,
+            size: 40,
+            collision_filter: 0b0001,
+            shape: TileShape::OneWay,
+            layers: BLOCK_LAYERS
+        }
+
+        block {
+            name: SlopeUpBlock,
+            path: "assets/tiles.png",
+            index: 14,
+            width: 80,
+            height: 80,
+            sprites_in_row: 7,
+            size: 40,
+            collision_filter: 0b0001,
+            shape: TileShape::Slope { left: 0.0, right: 1.0 },
+            layers: BLOCK_LAYERS
+        }
+
+        block {
+            name: SlopeDownBlock,
+            path: "assets/tiles.png",
+            index: 14,
+            width: 80,
+            height: 80,
+            sprites_in_row: 7,
+            size: 40,
+            collision_filter: 0b0001,
+            shape: TileShape::Slope { left: 1.0, right: 0.0 },
+            layers: BLOCK_LAYERS
         }
     }
 }
@@ -1,7 +1,8 @@
-use crate::actions::{ActorAction, ActorData, ActorMessage, ActorType};
+use crate::actions::{ActorAction, ActorData, ActorMessage, ActorType, layers};
 use mold2d::{
-    Actor, ActorIndex, ActorPosition, AnimatedSprite, BoundingBox, Collision, CollisionSide,
-    Context, PositionChange, Renderable, SpriteRectangle, Spritesheet, SpritesheetConfig, Viewport,
+    Actor, ActorIndex, ActorPosition, AnimatedSprite, BoundingBox, Circle, Collision,
+    CollisionLayers, CollisionSide, Context, PositionChange, Renderable, SpriteRectangle,
+    Spritesheet, SpritesheetConfig, TileShape, TriggerEvent, Viewport,
 };
 use sdl2::rect::Rect;
 use sdl2::render::Canvas;
@@ -41,6 +42,12 @@ impl Coin {
             animation: AnimatedSprite::with_fps(anims, fps),
         }
     }
+
+    /// The coin is round so it is collected only when something touches the coin
+    /// instead of the corners of its sprite
+    fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::Circle(Circle::from_rect(&self.rect.to_sdl()))
+    }
 }
 
 impl Actor for Coin {
@@ -50,8 +57,8 @@ impl Actor for Coin {
     fn handle_message(&mut self, message: &ActorMessage) -> ActorMessage {
         if let ActorMessage::ActorAction { ref action, .. } = *message {
             match *action {
-                // Action when player collides into item
-                ActorAction::Collision(ActorType::Player, _) => {
+                // Action when player touches the item
+                ActorAction::Trigger(ActorType::Player, TriggerEvent::Enter) => {
                     // Update score and remove coin
                     ActorMessage::MultipleMessages(vec![
                         Box::new(ActorMessage::UpdateScore(COIN_VALUE)),
@@ -74,7 +81,10 @@ impl Actor for Coin {
     }
 
     fn collides_with(&mut self, other_actor: &ActorData) -> Option<CollisionSide> {
-        self.rect.collides_with(&other_actor.rect)
+        match other_actor.bounding_box {
+            Some(ref bbox) => self.bounding_box().collides_with(bbox),
+            None => self.rect.collides_with(&other_actor.rect),
+        }
     }
 
     fn update(&mut self, _context: &mut Context, elapsed: f64) -> PositionChange {
@@ -104,7 +114,10 @@ impl Actor for Coin {
             collision_filter: 0b1111,
             resolves_collisions: false,
             rect: self.rect.to_sdl(),
-            bounding_box: Some(BoundingBox::Rectangle(self.rect)),
+            bounding_box: Some(self.bounding_box()),
+            shape: TileShape::Solid,
+            layers: CollisionLayers::new(layers::ITEM, 0),
+            trigger: true,
             actor_type: ActorType::Item,
         }
     }
@@ -1,12 +1,14 @@
-use crate::actions::{ActorAction, ActorData, ActorMessage, ActorType};
+use crate::actions::{ActorAction, ActorData, ActorMessage, ActorType, layers};
 use mold2d::{
-    Actor, ActorIndex, ActorPosition, Animations, BoundingBox, CollisionSide, Context, Direction,
-    PositionChange, SpriteRectangle, Spritesheet, SpritesheetConfig, Vector2D, Viewport,
+    Actor, ActorIndex, ActorPosition, Animations, BoundingBox, CollisionLayers, CollisionSide,
+    Context, Direction, PositionChange, SpriteRectangle, Spritesheet, SpritesheetConfig, Steering,
+    TileShape, TriggerEvent, Vector2D, Viewport,
 };
 use sdl2::{render::Canvas, video::Window};
 use std::error::Error;
 
-const KOOPA_X_MAXSPEED: f64 = 10.0;
+const KOOPA_WALK_SPEED: f64 = 2.0;
+const KOOPA_SHELL_SPEED: f64 = 10.0;
 const KOOPA_Y_MAXSPEED: f64 = 15.0;
 const KOOPA_ACCELERATION: f64 = 0.18;
 const KOOPA_SHELL_INVINCIBLE_FRAMES: i32 = 10;
@@ -32,7 +34,9 @@ pub struct Koopa {
     size: KoopaSize,
     direction: Direction,
     grounded: bool,
-    curr_speed: Vector2D,
+    /// True if the koopa is a shell that was kicked
+    kicked: bool,
+    steering: Steering,
     rect: SpriteRectangle,
     anims: Animations<(KoopaState, KoopaSize, Direction)>,
     invincibility_frames: i32,
@@ -99,12 +103,30 @@ impl Koopa {
             size: KoopaSize::Upright,
             direction: Direction::Left,
             grounded: false,
-            curr_speed: Vector2D { x: 0., y: 0. },
+            kicked: false,
+            steering: Steering::new(KOOPA_Y_MAXSPEED, KOOPA_ACCELERATION),
             rect: SpriteRectangle::new(position.0, position.1, KOOPA_WIDTH, KOOPA_HEIGHT),
             anims,
             invincibility_frames: 0,
         }
     }
+
+    /// Turns the koopa around without slowing it down
+    fn turn(&mut self, direction: Direction) {
+        if direction != self.direction {
+            self.direction = direction;
+            self.steering.velocity.x = -self.steering.velocity.x;
+        }
+    }
+
+    /// Turns the koopa away from the side that bumped into something
+    fn bounce_off(&mut self, side: CollisionSide) {
+        match side {
+            CollisionSide::Left => self.turn(Direction::Right),
+            CollisionSide::Right => self.turn(Direction::Left),
+            _ => {}
+        }
+    }
 }
 
 impl Actor for Koopa {
@@ -133,7 +155,7 @@ impl Actor for Koopa {
                         send_id: self.index,
                         recv_id: send_id,
                         action: ActorAction::Bounce(
-                            self.size == KoopaSize::Upright || self.curr_speed.x == 0.,
+                            self.size == KoopaSize::Upright || !self.kicked,
                         ),
                     }
                 }
@@ -152,14 +174,13 @@ impl Actor for Koopa {
                         let half_change = PositionChange::new().shrink_height_bot(amount);
                         self.rect.apply_change(&half_change);
                         self.size = KoopaSize::Shell;
+                        self.steering.velocity.x = 0.;
                         self.invincibility_frames = KOOPA_SHELL_INVINCIBLE_FRAMES;
                     } else if self.invincibility_frames == 0 {
                         // prevent kicking instantly after
-                        if self.curr_speed.x == 0. {
-                            self.curr_speed.x = -10.0;
-                        } else {
-                            self.curr_speed.x = 0.;
-                        }
+                        self.kicked = !self.kicked;
+                        self.direction = Direction::Right;
+                        self.steering.velocity.x = if self.kicked { KOOPA_SHELL_SPEED } else { 0. };
                         self.invincibility_frames = KOOPA_SHELL_INVINCIBLE_FRAMES;
                     }
 
@@ -171,33 +192,38 @@ impl Actor for Koopa {
                         recv_id: send_id,
                         action: ActorAction::DamageActor(0),
                     };
-                    if self.curr_speed.x != 0. {
-                        match actor_type {
-                            ActorType::Enemy | ActorType::Player => damage_message,
-                            ActorType::Item => {
-                                // Attempt to pick up item if kicked
-                                ActorMessage::ActorAction {
-                                    send_id: self.index,
-                                    recv_id: send_id,
-                                    action: ActorAction::DamageActor(0),
-                                }
-                            }
-                            ActorType::Block => {
-                                self.curr_speed.x = -self.curr_speed.x;
-                                ActorMessage::None
-                            }
-                        }
-                    } else {
-                        match actor_type {
-                            ActorType::Player => damage_message,
-                            ActorType::Enemy | ActorType::Block => {
-                                self.curr_speed.x = -self.curr_speed.x;
-                                ActorMessage::None
-                            }
-                            _ => ActorMessage::None,
+                    match actor_type {
+                        ActorType::Player => damage_message,
+                        // Kicked shells damage the enemies they run into
+                        ActorType::Enemy if self.kicked => damage_message,
+                        // Items are triggers so they are picked up when entered
+                        ActorType::Item => ActorMessage::None,
+                        ActorType::Enemy | ActorType::Block => {
+                            self.bounce_off(side);
+                            ActorMessage::None
                         }
                     }
                 }
+                Ledges { left, right } => {
+                    // Walking koopas turn around at the end of their platform
+                    // but not in the air where both sides have ledges
+                    let (ahead, behind, back) = match self.direction {
+                        Direction::Left => (left, right, Direction::Right),
+                        Direction::Right => (right, left, Direction::Left),
+                    };
+                    if self.size == KoopaSize::Upright && ahead && !behind {
+                        self.turn(back);
+                    }
+                    ActorMessage::None
+                }
+                Trigger(ActorType::Item, TriggerEvent::Enter) if self.kicked => {
+                    // Attempt to pick up item if kicked
+                    ActorMessage::ActorAction {
+                        send_id: self.index,
+                        recv_id: send_id,
+                        action: ActorAction::DamageActor(0),
+                    }
+                }
                 _ => ActorMessage::None,
             }
         } else {
@@ -211,23 +237,25 @@ impl Actor for Koopa {
     }
 
     fn update(&mut self, _context: &mut Context, _elapsed: f64) -> PositionChange {
-        let max_y_speed = if self.curr_state == KoopaState::Jumping {
+        // Walk or slide in the direction the koopa faces and fall when in the air
+        let speed = match self.size {
+            KoopaSize::Upright => KOOPA_WALK_SPEED,
+            KoopaSize::Shell if self.kicked => KOOPA_SHELL_SPEED,
+            KoopaSize::Shell => 0.,
+        };
+        let fall_speed = if self.curr_state == KoopaState::Jumping {
             KOOPA_Y_MAXSPEED
         } else {
             0.
         };
-
-        let target_speed = Vector2D {
-            x: 0.,
-            y: max_y_speed,
+        let desired = Vector2D {
+            x: match self.direction {
+                Direction::Left => -speed,
+                Direction::Right => speed,
+            },
+            y: fall_speed,
         };
-
-        self.curr_speed = (KOOPA_ACCELERATION * target_speed) + self.curr_speed;
-
-        let mut change = PositionChange::new().left(self.curr_speed.x as i32);
-        if self.curr_state == KoopaState::Jumping {
-            change = change.down(self.curr_speed.y as i32);
-        }
+        let change = self.steering.steer(desired);
 
         // If actor is no longer grounded, change it to jumping
         if !self.grounded && self.curr_state != KoopaState::Jumping {
@@ -270,6 +298,12 @@ impl Actor for Koopa {
                 .anims
                 .bbox(&(self.curr_state, self.size, self.direction))
                 .cloned(),
+            shape: TileShape::Solid,
+            layers: CollisionLayers::new(
+                layers::ENEMY,
+                layers::BLOCK | layers::ENEMY | layers::PLAYER | layers::ITEM,
+            ),
+            trigger: false,
             actor_type: ActorType::Enemy,
         }
     }
@@ -1,8 +1,8 @@
-use crate::actions::{ActorAction, ActorData, ActorMessage, ActorType};
+use crate::actions::{ActorAction, ActorData, ActorMessage, ActorType, layers};
 use mold2d::{
-    Actor, ActorIndex, ActorPosition, Animations, BoundingBox, CollisionSide, Context, Direction,
-    Polygon, PositionChange, Segment, SpriteRectangle, Spritesheet, SpritesheetConfig, Vector2D,
-    Viewport,
+    Actor, ActorIndex, ActorPosition, Animations, BoundingBox, CollisionLayers, CollisionSide,
+    Context, Direction, Polygon, PositionChange, Segment, SpriteRectangle, Spritesheet,
+    SpritesheetConfig, TileShape, Vector2D, Viewport,
 };
 use sdl2::pixels::Color;
 use sdl2::render::Canvas;
@@ -37,6 +37,10 @@ pub struct Player {
     direction: Direction,
     size: PlayerSize,
     grounded: bool,
+    /// True for the frame the player drops through a one-way platform
+    dropping: bool,
+    /// How far up the player's bounding box can grow before it hits a block
+    headroom: i32,
     curr_speed: Vector2D,
     rect: SpriteRectangle,
     anims: Animations<(PlayerSize, PlayerState, Direction)>,
@@ -117,6 +121,8 @@ impl Player {
             direction: Direction::Right,
             size: PlayerSize::Big,
             grounded: false,
+            dropping: false,
+            headroom: PLAYER_HEIGHT as i32,
             curr_speed: Vector2D { x: 0., y: 0. },
             rect: SpriteRectangle::new(position.0, position.1, PLAYER_WIDTH, PLAYER_HEIGHT),
             anims,
@@ -171,6 +177,10 @@ impl Actor for Player {
                     }
                     ActorMessage::None
                 }
+                ActorAction::Headroom(headroom) => {
+                    self.headroom = headroom;
+                    ActorMessage::None
+                }
                 ActorAction::Collision(_, CollisionSide::Top) => ActorMessage::None,
                 ActorAction::Collision(ActorType::Enemy, CollisionSide::Bottom) => {
                     // Ask actor if it can bounce on it
@@ -196,6 +206,10 @@ impl Actor for Player {
     }
 
     fn collides_with(&mut self, other: &ActorData) -> Option<CollisionSide> {
+        if self.dropping && other.shape == TileShape::OneWay {
+            return None;
+        }
+
         let key = (self.size, self.curr_state, self.direction);
         self.anims.collides_with(&key, &other.bounding_box)
     }
@@ -205,14 +219,21 @@ impl Actor for Player {
             if self.size == PlayerSize::Big && self.curr_state != PlayerState::Jumping {
                 self.size = PlayerSize::Crouching;
             }
-        } else if self.size == PlayerSize::Crouching {
-            // TODO(DarinM223): check if big player can fit by raycasting
+        } else if self.size == PlayerSize::Crouching && self.headroom >= PLAYER_HALF_HEIGHT as i32 {
+            // only stand up if the big player fits under the blocks above
             self.size = PlayerSize::Big;
         }
 
-        // Jump if space bar is pressed
+        // Drop through one-way platforms if down and space are pressed,
+        // otherwise jump if space bar is pressed
+        self.dropping = false;
         if context.events.event_called_once("SPACE") && self.curr_state != PlayerState::Jumping {
-            self.curr_speed.y = -PLAYER_JUMP_VELOCITY;
+            if context.events.event_called("DOWN") {
+                self.curr_speed.y = 0.;
+                self.dropping = true;
+            } else {
+                self.curr_speed.y = -PLAYER_JUMP_VELOCITY;
+            }
             self.curr_state = PlayerState::Jumping;
         }
 
@@ -317,6 +338,12 @@ impl Actor for Player {
                 .anims
                 .bbox(&(self.size, self.curr_state, self.direction))
                 .cloned(),
+            shape: TileShape::Solid,
+            layers: CollisionLayers::new(
+                layers::PLAYER,
+                layers::BLOCK | layers::ENEMY | layers::ITEM,
+            ),
+            trigger: false,
             actor_type: ActorType::Player,
         }
     }
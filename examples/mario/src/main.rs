@@ -2,9 +2,12 @@ pub mod actions;
 pub mod actors;
 pub mod views;
 
+use crate::actions::actor_from_token;
 use crate::views::game_view::GameView;
-use mold2d::Window;
 use mold2d::event_loop;
+use mold2d::{ActorToken, Broadphase, EditorView, View, Window};
+
+const LEVEL_PATH: &str = "levels/level1.txt";
 
 fn main() {
     let window = Window {
@@ -12,9 +15,35 @@ fn main() {
         width: 1024,
         height: 600,
     };
+    let edit = std::env::args().any(|arg| arg == "--edit");
+    let hot_reload = std::env::args().any(|arg| arg == "--hot-reload");
+    let quadtree = std::env::args().any(|arg| arg == "--quadtree");
 
     let result = event_loop::create_event_loop(window, |context| {
-        Box::new(GameView::new("levels/level1.txt", context))
+        if edit {
+            let palette = "PCKSG=-_~/\\".chars().map(ActorToken).collect();
+            let play_view = |level: &_, context: &mut _| -> Box<dyn View> {
+                Box::new(GameView::from_level(LEVEL_PATH, level, context))
+            };
+            let editor = EditorView::new(
+                LEVEL_PATH,
+                palette,
+                Box::new(actor_from_token),
+                Box::new(play_view),
+                "assets/belligerent.ttf",
+                context,
+            );
+            Box::new(editor.unwrap())
+        } else {
+            let mut view = GameView::new(LEVEL_PATH, context);
+            if quadtree {
+                view = view.with_broadphase(Broadphase::Quadtree);
+            }
+            if hot_reload {
+                view = view.with_hot_reload("assets");
+            }
+            Box::new(view)
+        }
     });
 
     match result {
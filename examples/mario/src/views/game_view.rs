@@ -1,50 +1,168 @@
-use crate::actions::{Actor, ActorAction, ActorMessage, ActorType};
-use crate::actions::{actor_from_token, handle_collision, handle_message, resolve_collision};
+use crate::actions::{Actor, ActorAction, ActorMessage, ActorType, layers};
+use crate::actions::{
+    actor_from_token, autotile_ground, handle_pair, move_actor, sprite_for_token, tile_for_token,
+};
 use crate::views::background_view::BackgroundView;
+use mold2d::cache;
 use mold2d::font;
 use mold2d::level;
-use mold2d::{ActorManager, Context, Quadtree, Sprite, View, ViewAction, Viewport};
+use mold2d::level::{GRID_SIZE, LayerKind, Level, LevelGrid};
+use mold2d::parallax;
+use mold2d::steering;
+use mold2d::streaming::CHUNK_SIZE;
+use mold2d::{
+    ActorManager, ActorToken, Broadphase, Context, Direction, FileWatcher, LevelStreamer,
+    ParallaxLayer, QueryFilter, Sprite, Tilemap, Vector2D, View, ViewAction, Viewport, World,
+};
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use std::error::Error;
+use std::io;
+
+/// Time in seconds between checking the level and assets for changes
+const HOT_RELOAD_INTERVAL: f64 = 0.5;
+/// The height of the blocks that actors on a slope walk onto instead of bumping into
+const STEP_HEIGHT: i32 = GRID_SIZE / 2;
+/// Most actors are about a block wide, which is the fastest
+/// cell size in the broadphase benchmark
+const DEFAULT_BROADPHASE: Broadphase = Broadphase::SpatialHash(GRID_SIZE);
 
 /// The main game view used for
 /// the actual gameplay
 pub struct GameView {
     actors: ActorManager<Actor>,
     viewport: Viewport,
+    streamer: LevelStreamer,
+    tilemap: Tilemap<ActorType>,
+    layers: Vec<ParallaxLayer>,
     level_path: String,
     cached_score: Option<String>,
     cached_font_sprite: Option<Sprite>,
+    watcher: Option<FileWatcher>,
+    world: World<ActorType>,
 }
 
 impl GameView {
     pub fn new(path: &str, context: &mut Context) -> GameView {
-        let level_result =
-            level::load_level(path, actor_from_token, &mut context.canvas, &context.window);
-        let (actors, viewport) = level_result.unwrap();
+        let level = Level::load(path).unwrap();
+        GameView::from_level(path, &level, context)
+    }
 
+    /// Creates a game view from a level that hasn't been saved yet
+    pub fn from_level(path: &str, level: &Level, context: &mut Context) -> GameView {
         if context.score.score("GAME_SCORE").is_none() {
             context.score.add_score("GAME_SCORE");
         }
 
-        GameView {
-            actors,
-            viewport,
+        let mut view = GameView {
+            actors: ActorManager::new(),
+            viewport: Viewport::new(&context.window, (0, 0)),
+            streamer: LevelStreamer::new(LevelGrid::new(), CHUNK_SIZE),
+            tilemap: Tilemap::new(0, 0),
+            layers: Vec::new(),
             level_path: path.to_owned(),
             cached_score: None,
             cached_font_sprite: None,
+            watcher: None,
+            world: World::new(Rect::new(0, 0, 1, 1), DEFAULT_BROADPHASE)
+                .with_step_height(STEP_HEIGHT),
+        };
+        view.set_level(level, context).unwrap();
+
+        view
+    }
+
+    /// Replaces the actors, tiles and layers with the ones in the level
+    fn set_level(&mut self, level: &Level, context: &mut Context) -> io::Result<()> {
+        let viewport = level::grid_viewport(level.main(), &context.window)?;
+
+        // blocks become tiles and everything else is spawned as actors
+        let mut tilemap = Tilemap::from_grid(level.main(), tile_for_token, &mut context.canvas);
+        autotile_ground(&mut tilemap, &mut context.canvas);
+        let mut actor_grid = level.main().clone();
+        actor_grid.retain(|token| tilemap.token_tile(token).is_none());
+
+        self.actors = ActorManager::new();
+        self.viewport = viewport;
+        self.streamer = LevelStreamer::new(actor_grid, CHUNK_SIZE);
+        // the player stays alive when it falls off the map
+        self.streamer
+            .set_persistent(|ActorToken(token)| token == 'P');
+        self.world.reset(tilemap.bounds());
+        self.tilemap = tilemap;
+        self.layers = parallax::parallax_layers(level, sprite_for_token, &mut context.canvas);
+        self.streamer.update(
+            &mut self.actors,
+            &self.viewport,
+            actor_from_token,
+            &mut context.canvas,
+        );
+
+        Ok(())
+    }
+
+    /// Finds the actors near each other with a different spatial index
+    pub fn with_broadphase(mut self, broadphase: Broadphase) -> GameView {
+        self.world = World::new(self.tilemap.bounds(), broadphase).with_step_height(STEP_HEIGHT);
+        self
+    }
+
+    /// Reloads the level and the assets when their files change
+    pub fn with_hot_reload(mut self, assets_path: &str) -> GameView {
+        let mut watcher = FileWatcher::new(HOT_RELOAD_INTERVAL);
+        watcher.watch(&self.level_path);
+        if let Err(e) = watcher.watch_dir(assets_path) {
+            println!("Could not watch assets in {}: {}", assets_path, e);
         }
+
+        self.watcher = Some(watcher);
+        self
+    }
+
+    /// Swaps changed images into the sprites loaded from them
+    /// and reloads the level only when the level file changed
+    fn hot_reload(
+        &mut self,
+        changed: &[String],
+        context: &mut Context,
+    ) -> Result<(), Box<dyn Error>> {
+        for path in changed {
+            if *path == self.level_path {
+                let level = Level::load(path)?;
+                self.set_level(&level, context)?;
+            } else {
+                cache::sprite_cache().reload(path, &context.canvas)?;
+            }
+        }
+
+        Ok(())
     }
 }
 
 impl View for GameView {
     #[inline]
     fn render(&mut self, context: &mut Context, elapsed: f64) -> Result<(), Box<dyn Error>> {
+        // reload the changed files before rendering them
+        let changed = match self.watcher {
+            Some(ref mut watcher) => watcher.update(elapsed),
+            None => Vec::new(),
+        };
+        self.hot_reload(&changed, context)?;
+
         // start off with a black screen
         context.canvas.set_draw_color(Color::RGB(135, 206, 250));
         context.canvas.clear();
 
+        // render background layers behind the actors
+        for layer in &self.layers {
+            if layer.kind() == LayerKind::Background {
+                layer.render(&self.viewport, &mut context.canvas)?;
+            }
+        }
+
+        // render the blocks in the level
+        self.tilemap.render(&self.viewport, &mut context.canvas)?;
+
         // render contained actors
         for actor in self.actors.values_mut() {
             if self.viewport.rect_in_viewport(&actor.data().rect) {
@@ -52,6 +170,13 @@ impl View for GameView {
             }
         }
 
+        // render foreground layers over the actors
+        for layer in &self.layers {
+            if layer.kind() == LayerKind::Foreground {
+                layer.render(&self.viewport, &mut context.canvas)?;
+            }
+        }
+
         // render score
         if let Some(score) = context.score.score("GAME_SCORE") {
             let score_text = format!("Score: {}", score);
@@ -94,69 +219,81 @@ impl View for GameView {
             return Some(ViewAction::ChangeView(Box::new(BackgroundView)));
         }
 
-        let window_rect = Rect::new(0, 0, context.window.width, context.window.height);
-        let viewport_clone = self.viewport.clone();
-        let mut quadtree = Quadtree::new(window_rect, &viewport_clone);
-        let mut keys = Vec::with_capacity(self.actors.len());
+        // spawn and despawn the actors near the viewport
+        self.streamer.update(
+            &mut self.actors,
+            &self.viewport,
+            actor_from_token,
+            &mut context.canvas,
+        );
+        self.tilemap.set_time(context.time);
+        for layer in &mut self.layers {
+            layer.set_time(context.time);
+        }
 
-        for (key, actor) in &mut self.actors.iter_mut() {
+        // tell the enemies where their platforms end
+        // so that they can turn around at the ledges
+        for actor in self.actors.values_mut() {
             let data = actor.data();
-
-            if self.viewport.rect_in_viewport(&data.rect) {
-                keys.push(key);
-                quadtree.insert(data);
+            if data.actor_type != ActorType::Enemy {
+                continue;
             }
-        }
 
-        for key in keys {
-            let mut collisions = [None; 6];
-            let mut collision_idx = 0;
-            if let Some(actor) = self.actors.get_mut(key) {
-                let data = actor.data();
-
-                // update the actor
-                let pos_change = actor.update(context, elapsed);
-                actor.handle_message(&ActorMessage::ActorAction {
-                    send_id: data.index,
-                    recv_id: data.index,
-                    action: ActorAction::ChangePosition(pos_change),
-                });
-
-                if data.collision_filter != 0 && data.actor_type != ActorType::Block {
-                    // only check collisions for nearby actors
-                    let nearby_actors = quadtree
-                        .retrieve(&data.rect)
-                        .into_iter()
-                        .cloned()
-                        .collect::<Vec<_>>();
-                    for other in nearby_actors {
-                        if let Some(direction) = actor.collides_with(&other) {
-                            resolve_collision(actor, &other, direction);
-                            collisions[collision_idx] = Some((data, other, direction));
-                            collision_idx += 1;
-                        }
-                    }
-                }
+            let blocked = |direction| {
+                steering::turn_at_ledge(&self.tilemap, &data.rect, direction) != direction
+            };
+            actor.handle_message(&ActorMessage::ActorAction {
+                send_id: data.index,
+                recv_id: data.index,
+                action: ActorAction::Ledges {
+                    left: blocked(Direction::Left),
+                    right: blocked(Direction::Right),
+                },
+            });
+        }
 
-                if data.actor_type == ActorType::Player {
-                    self.viewport.set_position((data.rect.x(), data.rect.y()));
-                }
+        // tell the player how far it can grow so that
+        // it only stands up when it fits under the blocks
+        let blocks = QueryFilter::new().mask(layers::BLOCK);
+        for actor in self.actors.values_mut() {
+            let data = actor.data();
+            if data.actor_type != ActorType::Player {
+                continue;
             }
+            let Some(bbox) = data.bounding_box else {
+                continue;
+            };
 
-            for collision in collisions.iter() {
-                if let Some((actor, other, direction)) = *collision {
-                    handle_collision(
-                        &actor,
-                        &other,
-                        direction,
-                        &handle_message,
-                        &mut self.actors,
-                        &mut self.viewport,
-                        context,
-                    );
-                } else {
-                    break;
-                }
+            let up = Vector2D {
+                x: 0.,
+                y: -f64::from(data.rect.height()),
+            };
+            let headroom = match self.world.shape_cast(&bbox, &up, &self.tilemap, &blocks) {
+                Some(hit) => (hit.time * -up.y).floor() as i32,
+                None => data.rect.height() as i32,
+            };
+            actor.handle_message(&ActorMessage::ActorAction {
+                send_id: data.index,
+                recv_id: data.index,
+                action: ActorAction::Headroom(headroom),
+            });
+        }
+
+        self.world.step(
+            &mut self.actors,
+            &self.tilemap,
+            &mut self.viewport,
+            context,
+            elapsed,
+            &move_actor,
+            &handle_pair,
+        );
+
+        // follow the player with the viewport
+        for actor in self.actors.values_mut() {
+            let data = actor.data();
+            if data.actor_type == ActorType::Player {
+                self.viewport.set_position((data.rect.x(), data.rect.y()));
             }
         }
 
@@ -6,7 +6,7 @@ pub struct ActorToken(pub char);
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct ActorPosition(pub i32, pub i32);
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub struct ActorIndex {
     pub id: usize,
     pub generation: usize,
@@ -173,7 +173,7 @@ impl<A: Actor + ?Sized> ActorManager<A> {
 mod tests {
     use super::*;
     use crate::ActorData;
-    use crate::collision::CollisionSide;
+    use crate::collision::{CollisionLayers, CollisionSide, TileShape};
     use crate::context::Context;
     use crate::vector::PositionChange;
     use crate::viewport::Viewport;
@@ -219,6 +219,9 @@ mod tests {
                 resolves_collisions: false,
                 rect: Rect::new(0, 0, 0, 0),
                 bounding_box: None,
+                shape: TileShape::Solid,
+                layers: CollisionLayers::default(),
+                trigger: false,
                 actor_type: (),
             }
         }
@@ -0,0 +1,141 @@
+/// Bit set when the tile above is the same terrain
+pub const NORTH: u8 = 0b0000_0001;
+/// Bit set when the tile to the right is the same terrain
+pub const EAST: u8 = 0b0000_0010;
+/// Bit set when the tile below is the same terrain
+pub const SOUTH: u8 = 0b0000_0100;
+/// Bit set when the tile to the left is the same terrain
+pub const WEST: u8 = 0b0000_1000;
+/// Bit set when the tile above and to the right is the same terrain
+pub const NORTH_EAST: u8 = 0b0001_0000;
+/// Bit set when the tile below and to the right is the same terrain
+pub const SOUTH_EAST: u8 = 0b0010_0000;
+/// Bit set when the tile below and to the left is the same terrain
+pub const SOUTH_WEST: u8 = 0b0100_0000;
+/// Bit set when the tile above and to the left is the same terrain
+pub const NORTH_WEST: u8 = 0b1000_0000;
+
+/// The neighbors that are checked when autotiling
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Neighborhood {
+    /// Only the tiles above, below, left and right
+    FourWay,
+    /// The four way neighbors and the diagonal tiles
+    EightWay,
+}
+
+impl Neighborhood {
+    /// Returns the offset in columns and rows for every neighbor bit
+    fn offsets(&self) -> &'static [(u8, i32, i32)] {
+        const OFFSETS: [(u8, i32, i32); 8] = [
+            (NORTH, 0, -1),
+            (EAST, 1, 0),
+            (SOUTH, 0, 1),
+            (WEST, -1, 0),
+            (NORTH_EAST, 1, -1),
+            (SOUTH_EAST, 1, 1),
+            (SOUTH_WEST, -1, 1),
+            (NORTH_WEST, -1, -1),
+        ];
+
+        match *self {
+            Neighborhood::FourWay => &OFFSETS[..4],
+            Neighborhood::EightWay => &OFFSETS,
+        }
+    }
+}
+
+/// Returns the neighbor bits for a grid cell given a function that
+/// returns true if the cell at a column and row is the same terrain
+pub fn neighbor_bits<F>(neighborhood: Neighborhood, col: i32, row: i32, same_terrain: F) -> u8
+where
+    F: Fn(i32, i32) -> bool,
+{
+    neighborhood
+        .offsets()
+        .iter()
+        .filter(|&&(_, x, y)| same_terrain(col + x, row + y))
+        .fold(0, |bits, &(bit, _, _)| bits | bit)
+}
+
+/// A rule that picks a spritesheet index when the neighbor bits
+/// in the mask are equal to the rule's bits
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutotileRule {
+    pub mask: u8,
+    pub bits: u8,
+    pub index: i32,
+}
+
+/// A set of rules that choose the sprite of a terrain tile
+/// based on its neighbors of the same terrain.
+///
+/// The first rule that matches is used, otherwise the default index is used.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutotileRules {
+    neighborhood: Neighborhood,
+    rules: Vec<AutotileRule>,
+    default_index: i32,
+}
+
+impl AutotileRules {
+    pub fn new(neighborhood: Neighborhood, default_index: i32) -> AutotileRules {
+        AutotileRules {
+            neighborhood,
+            rules: Vec::new(),
+            default_index,
+        }
+    }
+
+    /// Adds a rule that picks the index when the neighbor bits in the mask
+    /// are equal to the bits. Rules are checked in the order they are added.
+    pub fn add_rule(&mut self, mask: u8, bits: u8, index: i32) {
+        self.rules.push(AutotileRule { mask, bits, index });
+    }
+
+    pub fn neighborhood(&self) -> Neighborhood {
+        self.neighborhood
+    }
+
+    /// Returns the spritesheet index for a tile with the given neighbor bits
+    pub fn index(&self, bits: u8) -> i32 {
+        self.rules
+            .iter()
+            .find(|rule| bits & rule.mask == rule.bits)
+            .map_or(self.default_index, |rule| rule.index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neighbor_bits() {
+        // A plus shape with a missing left arm
+        let cells = [(1, 0), (2, 1), (1, 2), (1, 1), (2, 2)];
+        let same = |col, row| cells.contains(&(col, row));
+
+        assert_eq!(
+            neighbor_bits(Neighborhood::FourWay, 1, 1, same),
+            NORTH | EAST | SOUTH
+        );
+        assert_eq!(
+            neighbor_bits(Neighborhood::EightWay, 1, 1, same),
+            NORTH | EAST | SOUTH | SOUTH_EAST
+        );
+        assert_eq!(neighbor_bits(Neighborhood::EightWay, 5, 5, same), 0);
+    }
+
+    #[test]
+    fn test_rules() {
+        let mut rules = AutotileRules::new(Neighborhood::FourWay, 14);
+        rules.add_rule(NORTH | SOUTH, NORTH | SOUTH, 21);
+        rules.add_rule(NORTH, NORTH, 28);
+
+        assert_eq!(rules.index(0), 14);
+        assert_eq!(rules.index(SOUTH | EAST), 14);
+        assert_eq!(rules.index(NORTH | SOUTH | WEST), 21);
+        assert_eq!(rules.index(NORTH | WEST), 28);
+    }
+}
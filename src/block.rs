@@ -30,14 +30,31 @@
 ///             height: 5, // height of block
 ///             sprites_in_row: 10, // number of blocks in the spritesheet in a row
 ///             size: 20, // size of the rendered block
+///             collision_filter: 0b1111 // the sides that can be collided into
 ///         }
 ///
 ///         block {
+///             name: Platform,
+///             ....
+///             collision_filter: 0b0001,
+///             shape: ::mold2d::TileShape::OneWay, // the shape that actors stand on
+///             layers: ::mold2d::CollisionLayers::new(BLOCK, 0) // the collision layers
+///         }
+///
+///         block {
+///             name: WaterBlock,
+///             path: "assets/spritesheet.png",
+///             index: 30, // the index of the first frame
+///             frames: 4, // animates the sprites from index 30 to 33
+///             fps: 8.0, // the frames shown per second
 ///             ....
 ///         }
 ///     }
 /// }
 /// ```
+///
+/// Every block of an animated block type shows the same frame because
+/// the animation is driven by the time in the context.
 #[macro_export]
 macro_rules! block {
     (
@@ -49,11 +66,14 @@ macro_rules! block {
                     name: $name:ident,
                     path: $path:expr,
                     index: $index:expr,
+                    $(frames: $frames:expr, fps: $fps:expr,)?
                     width: $width:expr,
                     height: $height:expr,
                     sprites_in_row: $sprites_in_row:expr,
                     size: $size:expr,
                     collision_filter: $filter:expr
+                    $(, shape: $shape:expr)?
+                    $(, layers: $layers:expr)?
                 }
             )*
         }
@@ -61,7 +81,7 @@ macro_rules! block {
         $(
             pub struct $name {
                 pub rect: ::mold2d::SpriteRectangle,
-                pub sprite: ::mold2d::Sprite,
+                pub sprite: ::mold2d::AnimatedSprite,
                 index: ::mold2d::ActorIndex,
             }
 
@@ -78,9 +98,12 @@ macro_rules! block {
                         path: $path,
                     };
 
+                    let (frames, fps) = $crate::block!(@animation $($frames, $fps)?);
+
                     let anim = ::mold2d::Spritesheet::new(anim_data, canvas);
-                    let mut sprite_anims = anim.range($index, $index + 1);
-                    let sprite = sprite_anims.pop().unwrap();
+                    let sprite_anims = anim.range($index, $index + frames);
+                    assert!(!sprite_anims.is_empty());
+                    let sprite = ::mold2d::AnimatedSprite::with_fps(sprite_anims, fps);
 
                     $name {
                         index,
@@ -122,6 +145,7 @@ macro_rules! block {
                           viewport: &mut ::mold2d::Viewport,
                           _elapsed: f64) -> Result<(), Box<dyn ::std::error::Error>> {
                     use ::mold2d::Renderable;
+                    self.sprite.set_time(context.time);
                     let (rx, ry) = viewport.relative_point((self.rect.x, self.rect.y));
                     let rect = ::sdl2::rect::Rect::new(rx, ry, self.rect.w, self.rect.h);
 
@@ -137,10 +161,30 @@ macro_rules! block {
                         collision_filter: $filter,
                         rect: self.rect.to_sdl(),
                         bounding_box: Some(::mold2d::BoundingBox::Rectangle(self.rect.clone())),
+                        shape: {
+                            let shape = ::mold2d::TileShape::Solid;
+                            $(let shape = $shape;)?
+                            shape
+                        },
+                        layers: {
+                            let layers = ::mold2d::CollisionLayers::default();
+                            $(let layers = $layers;)?
+                            layers
+                        },
+                        trigger: false,
                         actor_type: $actor_type::Block,
                     }
                 }
             }
         )*
-    }
+    };
+
+    // The number of frames and the frames per second of a block,
+    // which is a single frame if the block isn't animated
+    (@animation) => {
+        (1, 1.0)
+    };
+    (@animation $frames:expr, $fps:expr) => {
+        ($frames, $fps)
+    };
 }
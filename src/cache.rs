@@ -1,7 +1,12 @@
 use crate::sprite::Sprite;
 use lazy_static::*;
+use sdl2::image::LoadTexture;
+use sdl2::render::Canvas;
 use sdl2::ttf::{Font, Sdl2TtfContext};
+use sdl2::video::Window;
 use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
 use std::mem;
 use std::sync::{Arc, Mutex, Once};
 
@@ -41,6 +46,44 @@ pub struct SpriteCache {
     pub cache: Arc<Mutex<HashMap<String, Sprite>>>,
 }
 
+impl SpriteCache {
+    /// Reads the file of a cached sprite again and swaps the new image into
+    /// the cached sprite, so that every sprite already loaded from the file
+    /// shows the new image. Returns false if no sprite was loaded from the file.
+    pub fn reload(&self, path: &str, canvas: &Canvas<Window>) -> Result<bool, Box<dyn Error>> {
+        // Every path of a file shares one texture so the file is only read once
+        let sprite = match self.cache.lock() {
+            Ok(ref cache) => cache
+                .iter()
+                .find(|(cached_path, _)| same_file(cached_path, path))
+                .map(|(_, sprite)| sprite.clone()),
+            Err(_) => None,
+        };
+
+        match sprite {
+            Some(sprite) => {
+                let creator = canvas.texture_creator();
+                sprite.replace_texture(creator.load_texture(path)?);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Returns true if two paths are the same file,
+/// like `./assets/koopa.png` and `assets/koopa.png`
+pub(crate) fn same_file(a: &str, b: &str) -> bool {
+    if a == b {
+        return true;
+    }
+
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
 /// Returns the sprite cache as a singleton
 pub fn sprite_cache() -> SpriteCache {
     static mut SINGLETON: *const SpriteCache = 0 as *const SpriteCache;
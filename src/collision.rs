@@ -1,5 +1,8 @@
+use super::ActorData;
+use crate::raycast::{self, Polygon, Segment};
+use crate::shape::{Circle, ConvexPolygon};
 use crate::sprite::SpriteRectangle;
-use crate::vector::PositionChange;
+use crate::vector::{PositionChange, Vector2D};
 use sdl2::rect::Rect;
 use std::mem;
 use std::ops::{BitAnd, BitOr};
@@ -134,6 +137,21 @@ impl From<u8> for CollisionSide {
     }
 }
 
+/// Returns the side of a shape that faces the direction of a normal
+pub fn normal_side(normal: &Vector2D) -> CollisionSide {
+    if normal.x.abs() > normal.y.abs() {
+        if normal.x < 0. {
+            CollisionSide::Left
+        } else {
+            CollisionSide::Right
+        }
+    } else if normal.y < 0. {
+        CollisionSide::Top
+    } else {
+        CollisionSide::Bottom
+    }
+}
+
 /// Prints the collision side as a byte
 pub fn print_collision_side_u8(direction: u8) {
     print!("Collisions: (");
@@ -156,77 +174,679 @@ pub fn print_collision_side_u8(direction: u8) {
     print!(")");
 }
 
+/// Where and how deep two objects touch, from the perspective of
+/// the object that checked for the collision
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    /// The unit normal that points in the direction the object
+    /// has to move to separate from the other object
+    pub normal: Vector2D,
+    /// How far the objects overlap along the normal.
+    /// Objects that only touch have a depth of zero.
+    pub depth: f64,
+    /// The point in the middle of the overlap where the objects touch
+    pub point: (f64, f64),
+    /// The side of the object that collided
+    pub side: CollisionSide,
+}
+
+impl Contact {
+    /// Returns the contact from the perspective of the other object
+    pub fn reversed(&self) -> Contact {
+        Contact {
+            normal: -1. * self.normal,
+            side: CollisionSide::from(CollisionSide::reverse(self.side)),
+            ..*self
+        }
+    }
+
+    /// Returns the smallest position change in whole pixels that
+    /// separates the objects, or None if they only touch
+    pub fn position_change(&self) -> Option<PositionChange> {
+        if self.depth <= 0. {
+            return None;
+        }
+
+        // Round away from zero so that the objects
+        // are apart after moving by whole pixels
+        let away_from_zero = |distance: f64| {
+            if distance.abs() < 1e-6 {
+                0
+            } else {
+                (distance.signum() * distance.abs().ceil()) as i32
+            }
+        };
+        Some(PositionChange {
+            x: away_from_zero(self.normal.x * self.depth),
+            y: away_from_zero(self.normal.y * self.depth),
+            ..PositionChange::new()
+        })
+    }
+}
+
 /// Checks collisions for different objects
 pub trait Collision<T> {
-    fn collides_with(&self, other: &T) -> Option<CollisionSide>;
+    /// Returns the contact with the other object or None if they don't touch
+    fn contact(&self, other: &T) -> Option<Contact>;
+
+    /// Returns the side of the collision or None if the objects don't touch
+    fn collides_with(&self, other: &T) -> Option<CollisionSide> {
+        self.contact(other).map(|contact| contact.side)
+    }
 }
 
 impl Collision<Rect> for Rect {
-    fn collides_with(&self, other: &Rect) -> Option<CollisionSide> {
+    fn contact(&self, other: &Rect) -> Option<Contact> {
         let w = 0.5 * f64::from(self.width() + other.width());
         let h = 0.5 * f64::from(self.height() + other.height());
         let dx = center_point(self).0 - center_point(other).0;
         let dy = center_point(self).1 - center_point(other).1;
 
-        if dx.abs() <= w && dy.abs() <= h {
-            let wy = w * dy;
-            let hx = h * dx;
-
-            if wy > hx && wy > -hx {
-                return Some(CollisionSide::Top);
-            } else if wy > hx {
-                return Some(CollisionSide::Right);
-            } else if wy <= hx && wy > -hx {
-                return Some(CollisionSide::Left);
-            } else {
-                return Some(CollisionSide::Bottom);
-            }
+        if dx.abs() > w || dy.abs() > h {
+            return None;
         }
 
-        None
+        let wy = w * dy;
+        let hx = h * dx;
+        let side = if wy > hx && wy > -hx {
+            CollisionSide::Top
+        } else if wy > hx {
+            CollisionSide::Right
+        } else if wy <= hx && wy > -hx {
+            CollisionSide::Left
+        } else {
+            CollisionSide::Bottom
+        };
+
+        let (normal, depth) = match side {
+            CollisionSide::Top => (Vector2D { x: 0., y: 1. }, h - dy.abs()),
+            CollisionSide::Bottom => (Vector2D { x: 0., y: -1. }, h - dy.abs()),
+            CollisionSide::Left => (Vector2D { x: 1., y: 0. }, w - dx.abs()),
+            CollisionSide::Right => (Vector2D { x: -1., y: 0. }, w - dx.abs()),
+        };
+        let left = self.left().max(other.left());
+        let right = self.right().min(other.right());
+        let top = self.top().max(other.top());
+        let bottom = self.bottom().min(other.bottom());
+        let point = (0.5 * f64::from(left + right), 0.5 * f64::from(top + bottom));
+
+        Some(Contact {
+            normal,
+            depth,
+            point,
+            side,
+        })
     }
 }
 
 impl Collision<SpriteRectangle> for Rect {
-    fn collides_with(&self, other: &SpriteRectangle) -> Option<CollisionSide> {
-        self.collides_with(&other.to_sdl())
+    fn contact(&self, other: &SpriteRectangle) -> Option<Contact> {
+        self.contact(&other.to_sdl())
     }
 }
 
 impl Collision<Rect> for SpriteRectangle {
-    fn collides_with(&self, other: &Rect) -> Option<CollisionSide> {
-        self.to_sdl().collides_with(other)
+    fn contact(&self, other: &Rect) -> Option<Contact> {
+        self.to_sdl().contact(other)
     }
 }
 
 impl Collision<SpriteRectangle> for SpriteRectangle {
-    fn collides_with(&self, other: &SpriteRectangle) -> Option<CollisionSide> {
-        self.collides_with(&other.to_sdl())
+    fn contact(&self, other: &SpriteRectangle) -> Option<Contact> {
+        self.contact(&other.to_sdl())
+    }
+}
+
+/// Returns the smallest position change that moves a rectangle out of
+/// another rectangle, or None if the rectangles don't overlap
+pub fn rect_penetration(rect: &Rect, other: &Rect) -> Option<PositionChange> {
+    let overlap_x = rect.right().min(other.right()) - rect.left().max(other.left());
+    let overlap_y = rect.bottom().min(other.bottom()) - rect.top().max(other.top());
+    if overlap_x <= 0 || overlap_y <= 0 {
+        return None;
+    }
+
+    let (x, y) = center_point(rect);
+    let (other_x, other_y) = center_point(other);
+    let change = if overlap_x < overlap_y {
+        if x < other_x {
+            PositionChange::new().left(overlap_x)
+        } else {
+            PositionChange::new().right(overlap_x)
+        }
+    } else if y < other_y {
+        PositionChange::new().up(overlap_y)
+    } else {
+        PositionChange::new().down(overlap_y)
+    };
+
+    Some(change)
+}
+
+/// Returns the side of an actor that collided given the position change
+/// that moves the actor out of the collision
+pub fn resolved_side(change: &PositionChange) -> Option<CollisionSide> {
+    if change.y < 0 {
+        Some(CollisionSide::Bottom)
+    } else if change.y > 0 {
+        Some(CollisionSide::Top)
+    } else if change.x < 0 {
+        Some(CollisionSide::Right)
+    } else if change.x > 0 {
+        Some(CollisionSide::Left)
+    } else {
+        None
+    }
+}
+
+/// Returns the position change that moves an actor out of another actor
+/// in one step using the minimum translation vector of their bounding boxes.
+///
+/// Returns None if the actor doesn't resolve collisions, if they don't overlap,
+/// if the other actor is a trigger or isn't solid, or if the side the actor collided on
+/// isn't in the other actor's collision filter.
+pub fn resolve_collision<Type>(
+    actor: &ActorData<Type>,
+    other: &ActorData<Type>,
+) -> Option<PositionChange> {
+    if !actor.resolves_collisions || other.trigger || other.shape != TileShape::Solid {
+        return None;
+    }
+
+    let change = match (actor.bounding_box, other.bounding_box) {
+        (Some(bbox), Some(other_bbox)) => bbox.penetration(&other_bbox),
+        _ => rect_penetration(&actor.rect, &other.rect),
+    }?;
+
+    let side = resolved_side(&change)?;
+    if side & other.collision_filter == 0 {
+        return None;
+    }
+
+    Some(change)
+}
+
+/// Returns the movement of an actor shortened so that the actor stops at
+/// the first solid actor in its way instead of moving through it.
+///
+/// The actor keeps moving along the side that it hit, so an actor
+/// falling diagonally onto the ground slides along the ground.
+/// Like resolve_collision, this only affects actors that resolve collisions
+/// and honors the collision filter of the other actors. Actors on layers that
+/// aren't in the actor's collision mask are ignored.
+pub fn sweep_collision<Type>(
+    actor: &ActorData<Type>,
+    change: &PositionChange,
+    others: &[ActorData<Type>],
+) -> PositionChange {
+    if !actor.resolves_collisions {
+        return change.clone();
+    }
+
+    let rect = bounding_rect(actor);
+    let mut movement = change.to_vector();
+    for _ in 0..2 {
+        let first_hit = others
+            .iter()
+            .filter(|other| !other.trigger && other.shape == TileShape::Solid)
+            .filter(|other| actor.layers.collides_with(&other.layers))
+            .filter_map(|other| {
+                let hit = raycast::sweep_rect(&rect, &movement, &bounding_rect(other))?;
+                let side = CollisionSide::reverse(hit.side?);
+                (side & other.collision_filter != 0).then_some(hit)
+            })
+            .min_by(|hit1, hit2| hit1.time.total_cmp(&hit2.time));
+
+        match first_hit {
+            Some(hit) => {
+                if hit.normal.x != 0. {
+                    movement.x = (movement.x * hit.time).trunc();
+                }
+                if hit.normal.y != 0. {
+                    movement.y = (movement.y * hit.time).trunc();
+                }
+            }
+            None => break,
+        }
+    }
+
+    PositionChange {
+        x: movement.x as i32,
+        y: movement.y as i32,
+        ..change.clone()
+    }
+}
+
+/// Returns the rectangle of an actor's bounding box or the
+/// actor's rectangle if it doesn't have a bounding box
+fn bounding_rect<Type>(actor: &ActorData<Type>) -> Rect {
+    match actor.bounding_box {
+        Some(bbox) => bbox.bounding_rect(),
+        None => actor.rect,
+    }
+}
+
+/// The layer that actors are on when they don't set their layers
+pub const DEFAULT_LAYER: u32 = 1;
+
+/// A mask that contains every layer
+pub const ALL_LAYERS: u32 = u32::MAX;
+
+/// The collision layers that an actor is on and the layers
+/// that the actor checks for collisions against.
+///
+/// Games name their layers with constants, like `const PLAYER: u32 = 1 << 0;`,
+/// so that "player projectiles hit enemies but not the player" is declared once
+/// as `CollisionLayers::new(PROJECTILE, ENEMY | BLOCK)` instead of checking
+/// actor types after every collision.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CollisionLayers {
+    /// The bits of the layers the actor is on
+    pub layer: u32,
+    /// The bits of the layers the actor collides with,
+    /// 0 for actors that never check for collisions themselves
+    pub mask: u32,
+}
+
+impl CollisionLayers {
+    pub fn new(layer: u32, mask: u32) -> CollisionLayers {
+        CollisionLayers { layer, mask }
+    }
+
+    /// Returns true if an actor with these layers checks
+    /// for collisions against an actor with the other layers
+    pub fn collides_with(&self, other: &CollisionLayers) -> bool {
+        self.mask & other.layer != 0
+    }
+}
+
+impl Default for CollisionLayers {
+    fn default() -> CollisionLayers {
+        CollisionLayers::new(DEFAULT_LAYER, ALL_LAYERS)
+    }
+}
+
+/// The shape of a tile or block that actors stand on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TileShape {
+    /// A tile that is solid on every side
+    Solid,
+    /// A platform that actors only land on when coming from above,
+    /// so they can jump through it from below and drop through it
+    OneWay,
+    /// A slope that actors stand on going from the left edge to the right edge.
+    /// The heights are fractions of the tile height from the bottom of the tile,
+    /// so `Slope { left: 0.0, right: 1.0 }` is a 45° slope going up to the right
+    /// and `Slope { left: 0.0, right: 0.5 }` next to `Slope { left: 0.5, right: 1.0 }`
+    /// is a 22.5° slope over two tiles.
+    Slope { left: f64, right: f64 },
+}
+
+impl TileShape {
+    /// Returns the y coordinate of the top of a tile at a x coordinate
+    /// that is clamped to the tile
+    pub fn surface_y(&self, tile: &Rect, x: i32) -> i32 {
+        let height = match *self {
+            TileShape::Solid | TileShape::OneWay => 1.0,
+            TileShape::Slope { left, right } => {
+                let t = f64::from(x - tile.x()) / f64::from(tile.width());
+                left + (right - left) * t.clamp(0.0, 1.0)
+            }
+        };
+
+        tile.bottom() - (height * f64::from(tile.height())).round() as i32
+    }
+
+    /// Returns the shape of a slope tile as a polygon, or None
+    /// for the tiles that fill their whole rectangle
+    pub fn polygon(&self, tile: &Rect) -> Option<ConvexPolygon> {
+        let TileShape::Slope { .. } = *self else {
+            return None;
+        };
+
+        let (left, right) = (tile.left(), tile.right());
+        let corners = [
+            (left, self.surface_y(tile, left)),
+            (left, tile.bottom()),
+            (right, tile.bottom()),
+            (right, self.surface_y(tile, right)),
+        ];
+        let mut points: Vec<(f64, f64)> = Vec::with_capacity(corners.len());
+        for (x, y) in corners {
+            let point = (f64::from(x), f64::from(y));
+            if !points.contains(&point) {
+                points.push(point);
+            }
+        }
+        (points.len() >= 3).then(|| ConvexPolygon::new(&points))
+    }
+
+    /// Returns the distance an actor has to move up to stand on a one-way
+    /// platform or slope given the actor's rectangle before and after it moved,
+    /// or None if the actor isn't standing on the tile.
+    ///
+    /// Actors stand on slopes at the center of their bottom edge and stay
+    /// on a slope when walking down it, so the distance is negative when
+    /// the actor has to move down. Solid tiles always return None because
+    /// they are resolved like rectangles.
+    pub fn landing(&self, tile: &Rect, prev: &Rect, rect: &Rect) -> Option<i32> {
+        match *self {
+            TileShape::Solid => None,
+            TileShape::OneWay => {
+                let above = rect.right() > tile.left() && rect.left() < tile.right();
+                let landed = prev.bottom() <= tile.top() && rect.bottom() >= tile.top();
+                (above && landed).then(|| rect.bottom() - tile.top())
+            }
+            TileShape::Slope { .. } => {
+                let (x, prev_x) = (rect.center().x(), prev.center().x());
+                if x < tile.left() || x >= tile.right() || prev.bottom() > tile.bottom() {
+                    return None;
+                }
+
+                let surface = self.surface_y(tile, x);
+                let was_standing = (prev.bottom() - self.surface_y(tile, prev_x)).abs() <= 1;
+                let max_drop = (x - prev_x).abs() + 1;
+                if rect.bottom() >= surface || (was_standing && surface - rect.bottom() <= max_drop)
+                {
+                    Some(rect.bottom() - surface)
+                } else {
+                    None
+                }
+            }
+        }
     }
 }
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum BoundingBox {
     Rectangle(SpriteRectangle),
+    Circle(Circle),
+    ConvexPolygon(ConvexPolygon),
+}
+
+/// A bounding box as a shape that can be tested with the separating axis theorem
+enum SatShape {
+    Circle(Circle),
+    Polygon(ConvexPolygon),
+}
+
+impl SatShape {
+    fn project(&self, axis: &Vector2D) -> (f64, f64) {
+        match *self {
+            SatShape::Circle(ref circle) => circle.project(axis),
+            SatShape::Polygon(ref polygon) => polygon.project(axis),
+        }
+    }
+}
+
+/// Returns the direction and distance that the first shape has to move
+/// to separate from the second shape along the axis with the smallest
+/// overlap, or None if the shapes are apart on one of the axes
+fn separate_on_axes(
+    shape: &SatShape,
+    other: &SatShape,
+    axes: &[Vector2D],
+) -> Option<(Vector2D, f64)> {
+    let mut separation: Option<(Vector2D, f64)> = None;
+    for axis in axes {
+        let (min, max) = shape.project(axis);
+        let (other_min, other_max) = other.project(axis);
+        let forward = other_max - min;
+        let backward = max - other_min;
+        if forward < 0. || backward < 0. {
+            return None;
+        }
+
+        let (normal, depth) = if forward < backward {
+            (*axis, forward)
+        } else {
+            (-1. * *axis, backward)
+        };
+        if separation.is_none_or(|(_, min_depth)| depth < min_depth) {
+            separation = Some((normal, depth));
+        }
+    }
+
+    separation
+}
+
+/// Returns the separation of a circle from a polygon
+fn separate_circle(circle: &Circle, polygon: &ConvexPolygon) -> Option<(Vector2D, f64)> {
+    let mut axes = polygon.normals();
+
+    // The axis from the closest point of the polygon to the center of
+    // the circle separates the circle from the corners of the polygon
+    let distance = |point: &&(f64, f64)| {
+        (point.0 - circle.center.0).powi(2) + (point.1 - circle.center.1).powi(2)
+    };
+    if let Some(closest) = polygon
+        .points()
+        .iter()
+        .min_by(|p1, p2| distance(p1).total_cmp(&distance(p2)))
+    {
+        let axis = Vector2D {
+            x: circle.center.0 - closest.0,
+            y: circle.center.1 - closest.1,
+        };
+        if axis.length() > 0. {
+            axes.push(axis.normalize());
+        }
+    }
+
+    separate_on_axes(
+        &SatShape::Circle(*circle),
+        &SatShape::Polygon(*polygon),
+        &axes,
+    )
+}
+
+/// Returns the point in the middle of the overlap of two polygons
+/// given the normal and distance that separates the first polygon
+/// from the second polygon
+fn polygon_contact_point(
+    polygon: &ConvexPolygon,
+    other: &ConvexPolygon,
+    normal: &Vector2D,
+    depth: f64,
+) -> (f64, f64) {
+    let project = |point: &(f64, f64), axis: &Vector2D| point.0 * axis.x + point.1 * axis.y;
+
+    // The points of a polygon that are the furthest inside the other polygon
+    let deepest = |polygon: &ConvexPolygon, axis: &Vector2D| -> Vec<(f64, f64)> {
+        let min = polygon
+            .points()
+            .iter()
+            .map(|point| project(point, axis))
+            .fold(f64::MAX, f64::min);
+        polygon
+            .points()
+            .iter()
+            .filter(|point| project(point, axis) <= min + 1e-6)
+            .cloned()
+            .collect()
+    };
+
+    let points = deepest(polygon, normal);
+    let other_points = deepest(other, &(-1. * *normal));
+    let half = 0.5 * depth;
+    if points.len() == 1 {
+        return (points[0].0 + normal.x * half, points[0].1 + normal.y * half);
+    } else if other_points.len() == 1 {
+        return (
+            other_points[0].0 - normal.x * half,
+            other_points[0].1 - normal.y * half,
+        );
+    }
+
+    // Two sides are touching so the contact is in the middle
+    // of where the sides overlap
+    let tangent = Vector2D {
+        x: -normal.y,
+        y: normal.x,
+    };
+    let range = |points: &[(f64, f64)]| {
+        points
+            .iter()
+            .map(|point| project(point, &tangent))
+            .fold((f64::MAX, f64::MIN), |(min, max), t| {
+                (min.min(t), max.max(t))
+            })
+    };
+    let (min, max) = range(&points);
+    let (other_min, other_max) = range(&other_points);
+    let along = 0.5 * (min.max(other_min) + max.min(other_max)) - project(&points[0], &tangent);
+
+    (
+        points[0].0 + tangent.x * along + normal.x * half,
+        points[0].1 + tangent.y * along + normal.y * half,
+    )
 }
 
 impl BoundingBox {
+    /// Returns the smallest rectangle that contains the bounding box
+    pub fn bounding_rect(&self) -> Rect {
+        match *self {
+            BoundingBox::Rectangle(ref rect) => rect.to_sdl(),
+            BoundingBox::Circle(ref circle) => circle.bounding_rect(),
+            BoundingBox::ConvexPolygon(ref polygon) => polygon.bounding_rect(),
+        }
+    }
+
+    /// Returns the center point of the bounding box
+    pub fn center(&self) -> (f64, f64) {
+        match *self {
+            BoundingBox::Rectangle(ref rect) => center_point(&rect.to_sdl()),
+            BoundingBox::Circle(ref circle) => circle.center,
+            BoundingBox::ConvexPolygon(ref polygon) => polygon.center(),
+        }
+    }
+
+    fn sat_shape(&self) -> SatShape {
+        match *self {
+            BoundingBox::Rectangle(ref rect) => {
+                SatShape::Polygon(ConvexPolygon::from_rect(&rect.to_sdl()))
+            }
+            BoundingBox::Circle(circle) => SatShape::Circle(circle),
+            BoundingBox::ConvexPolygon(polygon) => SatShape::Polygon(polygon),
+        }
+    }
+
+    /// Returns the unit normal and the distance that the bounding box has to
+    /// move along the normal to separate from another bounding box using
+    /// the separating axis theorem, or None if they don't touch.
+    ///
+    /// Bounding boxes that only touch return a distance of zero.
+    pub fn separation(&self, other: &BoundingBox) -> Option<(Vector2D, f64)> {
+        match (self.sat_shape(), other.sat_shape()) {
+            (SatShape::Circle(circle1), SatShape::Circle(circle2)) => {
+                let between = Vector2D {
+                    x: circle1.center.0 - circle2.center.0,
+                    y: circle1.center.1 - circle2.center.1,
+                };
+                let distance = between.length();
+                let depth = circle1.radius + circle2.radius - distance;
+                if depth < 0. {
+                    return None;
+                }
+
+                let normal = if distance > 0. {
+                    between / distance
+                } else {
+                    Vector2D { x: 0., y: -1. }
+                };
+                Some((normal, depth))
+            }
+            (SatShape::Circle(circle), SatShape::Polygon(polygon)) => {
+                separate_circle(&circle, &polygon)
+            }
+            (SatShape::Polygon(polygon), SatShape::Circle(circle)) => {
+                separate_circle(&circle, &polygon).map(|(normal, depth)| (-1. * normal, depth))
+            }
+            (SatShape::Polygon(polygon1), SatShape::Polygon(polygon2)) => {
+                let mut axes = polygon1.normals();
+                axes.extend(polygon2.normals());
+                separate_on_axes(
+                    &SatShape::Polygon(polygon1),
+                    &SatShape::Polygon(polygon2),
+                    &axes,
+                )
+            }
+        }
+    }
+
+    /// Returns the smallest position change that moves the bounding box
+    /// out of another bounding box, or None if they don't overlap
+    pub fn penetration(&self, other: &BoundingBox) -> Option<PositionChange> {
+        match (self, other) {
+            (BoundingBox::Rectangle(rect1), BoundingBox::Rectangle(rect2)) => {
+                rect_penetration(&rect1.to_sdl(), &rect2.to_sdl())
+            }
+            _ => self.contact(other)?.position_change(),
+        }
+    }
+
     pub fn apply_change(&mut self, change: &PositionChange) {
         match *self {
             BoundingBox::Rectangle(ref mut rect) => {
                 rect.x += change.x;
                 rect.y += change.y;
             }
+            BoundingBox::Circle(ref mut circle) => circle.apply_change(change),
+            BoundingBox::ConvexPolygon(ref mut polygon) => polygon.apply_change(change),
         }
     }
 }
 
 impl Collision<BoundingBox> for BoundingBox {
-    fn collides_with(&self, other: &BoundingBox) -> Option<CollisionSide> {
-        match (self, other) {
-            (BoundingBox::Rectangle(rect1), BoundingBox::Rectangle(rect2)) => {
-                rect1.collides_with(rect2)
+    fn contact(&self, other: &BoundingBox) -> Option<Contact> {
+        if let (BoundingBox::Rectangle(rect1), BoundingBox::Rectangle(rect2)) = (self, other) {
+            return rect1.contact(rect2);
+        }
+
+        let (normal, depth) = self.separation(other)?;
+        let point = match (self.sat_shape(), other.sat_shape()) {
+            (SatShape::Circle(circle), _) => {
+                let distance = circle.radius - 0.5 * depth;
+                (
+                    circle.center.0 - normal.x * distance,
+                    circle.center.1 - normal.y * distance,
+                )
             }
+            (_, SatShape::Circle(circle)) => {
+                let distance = circle.radius - 0.5 * depth;
+                (
+                    circle.center.0 + normal.x * distance,
+                    circle.center.1 + normal.y * distance,
+                )
+            }
+            (SatShape::Polygon(polygon1), SatShape::Polygon(polygon2)) => {
+                polygon_contact_point(&polygon1, &polygon2, &normal, depth)
+            }
+        };
+
+        Some(Contact {
+            normal,
+            depth,
+            point,
+            side: normal_side(&(-1. * normal)),
+        })
+    }
+}
+
+impl Polygon for BoundingBox {
+    fn sides(&self) -> Vec<Segment> {
+        match *self {
+            BoundingBox::Rectangle(ref rect) => rect.to_sdl().sides(),
+            BoundingBox::Circle(ref circle) => circle.sides(),
+            BoundingBox::ConvexPolygon(ref polygon) => polygon.sides(),
+        }
+    }
+
+    fn collision_from_side(&self, id: usize) -> Option<CollisionSide> {
+        match *self {
+            BoundingBox::Rectangle(ref rect) => rect.to_sdl().collision_from_side(id),
+            BoundingBox::Circle(ref circle) => circle.collision_from_side(id),
+            BoundingBox::ConvexPolygon(ref polygon) => polygon.collision_from_side(id),
         }
     }
 }
@@ -234,6 +854,7 @@ impl Collision<BoundingBox> for BoundingBox {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::actor_data;
     use sdl2::rect::Rect;
 
     #[test]
@@ -280,4 +901,279 @@ mod tests {
         );
         assert_eq!(down_rect.collides_with(&up_rect), Some(CollisionSide::Top));
     }
+
+    fn solid(rect: Rect, collision_filter: u8) -> ActorData<()> {
+        ActorData {
+            collision_filter,
+            resolves_collisions: true,
+            bounding_box: Some(BoundingBox::Rectangle(SpriteRectangle::from_rect(rect))),
+            ..actor_data(0, rect)
+        }
+    }
+
+    #[test]
+    fn test_rect_penetration() {
+        let block = Rect::new(0, 40, 40, 40);
+
+        // Falling into the top of the block
+        let change = rect_penetration(&Rect::new(10, 5, 20, 40), &block).unwrap();
+        assert_eq!((change.x, change.y), (0, -5));
+        assert_eq!(resolved_side(&change), Some(CollisionSide::Bottom));
+
+        // Walking into the left side of the block
+        let change = rect_penetration(&Rect::new(-15, 45, 20, 30), &block).unwrap();
+        assert_eq!((change.x, change.y), (-5, 0));
+        assert_eq!(resolved_side(&change), Some(CollisionSide::Right));
+
+        // Touching rectangles don't need to be moved
+        assert!(rect_penetration(&Rect::new(0, 0, 40, 40), &block).is_none());
+    }
+
+    #[test]
+    fn test_resolve_collision() {
+        let block = solid(Rect::new(0, 40, 40, 40), 0b1111);
+        let actor = solid(Rect::new(10, 5, 20, 40), 0b1111);
+        let change = resolve_collision(&actor, &block).unwrap();
+        assert_eq!((change.x, change.y), (0, -5));
+
+        // The block can't be landed on from above
+        let block = solid(Rect::new(0, 40, 40, 40), 0b1110);
+        assert!(resolve_collision(&actor, &block).is_none());
+
+        // Actors pass through triggers
+        let trigger = ActorData {
+            trigger: true,
+            ..solid(Rect::new(0, 40, 40, 40), 0b1111)
+        };
+        assert!(resolve_collision(&actor, &trigger).is_none());
+
+        let actor = ActorData {
+            resolves_collisions: false,
+            ..actor
+        };
+        assert!(resolve_collision(&actor, &block).is_none());
+    }
+
+    #[test]
+    fn test_sweep_collision() {
+        let ground = solid(Rect::new(0, 100, 200, 40), 0b1111);
+        let actor = solid(Rect::new(0, 0, 20, 40), 0b1111);
+
+        // Falling fast stops at the ground
+        let change = PositionChange::new().down(70);
+        let swept = sweep_collision(&actor, &change, &[ground]);
+        assert_eq!((swept.x, swept.y), (0, 60));
+
+        // Falling diagonally slides along the ground
+        let change = PositionChange::new().down(120).right(30);
+        let swept = sweep_collision(&actor, &change, &[ground]);
+        assert_eq!((swept.x, swept.y), (30, 60));
+
+        // Platforms that can't be landed on don't stop the actor
+        let platform = solid(Rect::new(0, 100, 200, 40), 0b1110);
+        let swept = sweep_collision(&actor, &change, &[platform]);
+        assert_eq!((swept.x, swept.y), (30, 120));
+    }
+
+    #[test]
+    fn test_collision_layers() {
+        const PLAYER: u32 = 1 << 0;
+        const ENEMY: u32 = 1 << 1;
+        const PROJECTILE: u32 = 1 << 2;
+
+        let player = CollisionLayers::new(PLAYER, ENEMY);
+        let enemy = CollisionLayers::new(ENEMY, ALL_LAYERS);
+        let projectile = CollisionLayers::new(PROJECTILE, ENEMY);
+        assert!(projectile.collides_with(&enemy));
+        assert!(!projectile.collides_with(&player));
+        assert!(!player.collides_with(&projectile));
+        assert!(enemy.collides_with(&projectile));
+
+        // Actors move through the actors on layers they don't collide with
+        let ground = ActorData {
+            layers: CollisionLayers::new(ENEMY, 0),
+            ..solid(Rect::new(0, 100, 200, 40), 0b1111)
+        };
+        let actor = ActorData {
+            layers: player,
+            ..solid(Rect::new(0, 0, 20, 40), 0b1111)
+        };
+        let change = PositionChange::new().down(70);
+        let swept = sweep_collision(&actor, &change, &[ground]);
+        assert_eq!((swept.x, swept.y), (0, 60));
+
+        let actor = ActorData {
+            layers: projectile,
+            ..actor
+        };
+        let ground = ActorData {
+            layers: CollisionLayers::new(PLAYER, 0),
+            ..ground
+        };
+        let swept = sweep_collision(&actor, &change, &[ground]);
+        assert_eq!((swept.x, swept.y), (0, 70));
+    }
+
+    #[test]
+    fn test_surface_y() {
+        let tile = Rect::new(0, 40, 40, 40);
+        let slope = TileShape::Slope {
+            left: 0.0,
+            right: 0.5,
+        };
+
+        assert_eq!(TileShape::OneWay.surface_y(&tile, 20), 40);
+        assert_eq!(slope.surface_y(&tile, 0), 80);
+        assert_eq!(slope.surface_y(&tile, 20), 70);
+        assert_eq!(slope.surface_y(&tile, 100), 60);
+    }
+
+    #[test]
+    fn test_one_way_landing() {
+        let tile = Rect::new(0, 40, 40, 40);
+        let platform = TileShape::OneWay;
+
+        // Falling onto the platform from above
+        let prev = Rect::new(0, 0, 20, 30);
+        let rect = Rect::new(0, 15, 20, 30);
+        assert_eq!(platform.landing(&tile, &prev, &rect), Some(5));
+
+        // Standing on the platform
+        let rect = Rect::new(5, 10, 20, 30);
+        assert_eq!(platform.landing(&tile, &prev, &rect), Some(0));
+
+        // Jumping up through the platform
+        let prev = Rect::new(0, 50, 20, 30);
+        let rect = Rect::new(0, 30, 20, 30);
+        assert_eq!(platform.landing(&tile, &prev, &rect), None);
+    }
+
+    #[test]
+    fn test_slope_landing() {
+        let tile = Rect::new(0, 40, 40, 40);
+        let slope = TileShape::Slope {
+            left: 0.0,
+            right: 1.0,
+        };
+
+        // Walking up the slope moves the actor up to the surface
+        let prev = Rect::new(-10, 50, 20, 30);
+        let rect = Rect::new(0, 50, 20, 30);
+        assert_eq!(slope.landing(&tile, &prev, &rect), Some(10));
+
+        // Walking down the slope keeps the actor on the surface
+        let down_slope = TileShape::Slope {
+            left: 1.0,
+            right: 0.0,
+        };
+        let prev = Rect::new(0, 20, 20, 30);
+        let rect = Rect::new(5, 20, 20, 30);
+        assert_eq!(down_slope.landing(&tile, &prev, &rect), Some(-5));
+
+        // Jumping off the slope leaves the surface
+        let rect = Rect::new(5, 0, 20, 30);
+        assert_eq!(down_slope.landing(&tile, &prev, &rect), None);
+    }
+
+    #[test]
+    fn test_circle_collision() {
+        let circle = BoundingBox::Circle(Circle::new((0., 0.), 5.));
+        let other = BoundingBox::Circle(Circle::new((8., 0.), 5.));
+        let (normal, depth) = circle.separation(&other).unwrap();
+        assert_eq!(normal, Vector2D { x: -1., y: 0. });
+        assert_eq!(depth, 2.);
+        assert_eq!(circle.collides_with(&other), Some(CollisionSide::Right));
+
+        let apart = BoundingBox::Circle(Circle::new((11., 0.), 5.));
+        assert_eq!(circle.collides_with(&apart), None);
+
+        // A circle falling onto a rectangle collides on its bottom
+        let circle = BoundingBox::Circle(Circle::new((10., -4.), 5.));
+        let rect = BoundingBox::Rectangle(SpriteRectangle::new(0, 0, 20, 20));
+        let contact = circle.contact(&rect).unwrap();
+        assert_eq!((contact.side, contact.depth), (CollisionSide::Bottom, 1.));
+        let contact = rect.contact(&circle).unwrap();
+        assert_eq!((contact.side, contact.depth), (CollisionSide::Top, 1.));
+        assert_eq!(circle.penetration(&rect), Some(PositionChange::new().up(1)));
+    }
+
+    #[test]
+    fn test_polygon_collision() {
+        let slope =
+            BoundingBox::ConvexPolygon(ConvexPolygon::new(&[(0., 20.), (20., 0.), (20., 20.)]));
+
+        // The corner of the rectangle is pushed out of the slope diagonally
+        let rect = BoundingBox::Rectangle(SpriteRectangle::new(0, 1, 10, 10));
+        let contact = rect.contact(&slope).unwrap();
+        assert_eq!(contact.side, CollisionSide::Bottom);
+        assert!((contact.depth - 0.5_f64.sqrt()).abs() < 1e-9);
+        assert_eq!(
+            rect.penetration(&slope),
+            Some(PositionChange::new().left(1).up(1))
+        );
+
+        // The rectangle overlaps the bounding rectangle of the slope but not the slope
+        let rect = BoundingBox::Rectangle(SpriteRectangle::new(0, -1, 10, 10));
+        assert_eq!(rect.collides_with(&slope), None);
+        assert_eq!(rect.penetration(&slope), None);
+    }
+
+    #[test]
+    fn test_rect_contact() {
+        let rect = Rect::new(0, 0, 10, 10);
+        let contact = rect.contact(&Rect::new(8, 2, 10, 10)).unwrap();
+        assert_eq!(contact.side, CollisionSide::Right);
+        assert_eq!(contact.normal, Vector2D { x: -1., y: 0. });
+        assert_eq!(contact.depth, 2.);
+        assert_eq!(contact.point, (9., 6.));
+        assert_eq!(
+            contact.position_change(),
+            Some(PositionChange::new().left(2))
+        );
+
+        let reversed = contact.reversed();
+        assert_eq!(reversed.side, CollisionSide::Left);
+        assert_eq!(reversed.normal, Vector2D { x: 1., y: 0. });
+
+        // Touching rectangles collide without overlapping
+        let contact = rect.contact(&Rect::new(0, 10, 10, 10)).unwrap();
+        assert_eq!(contact.side, CollisionSide::Bottom);
+        assert_eq!(contact.depth, 0.);
+        assert_eq!(contact.position_change(), None);
+    }
+
+    #[test]
+    fn test_shape_contact() {
+        let circle = BoundingBox::Circle(Circle::new((10., -4.), 5.));
+        let rect = BoundingBox::Rectangle(SpriteRectangle::new(0, 0, 20, 20));
+        assert_eq!(circle.contact(&rect).unwrap().point, (10., 0.5));
+        assert_eq!(rect.contact(&circle).unwrap().point, (10., 0.5));
+
+        let slope =
+            BoundingBox::ConvexPolygon(ConvexPolygon::new(&[(0., 20.), (20., 0.), (20., 20.)]));
+        let rect = BoundingBox::Rectangle(SpriteRectangle::new(0, 1, 10, 10));
+        let (x, y) = rect.contact(&slope).unwrap().point;
+        assert!((x - 9.75).abs() < 1e-9 && (y - 10.75).abs() < 1e-9);
+
+        // The contact between two sides is in the middle of where they overlap
+        let polygon =
+            BoundingBox::ConvexPolygon(ConvexPolygon::from_rect(&Rect::new(0, 0, 10, 10)));
+        let other = BoundingBox::ConvexPolygon(ConvexPolygon::from_rect(&Rect::new(4, 9, 10, 10)));
+        let contact = polygon.contact(&other).unwrap();
+        assert_eq!(contact.side, CollisionSide::Bottom);
+        assert_eq!(contact.point, (7., 9.5));
+    }
+
+    #[test]
+    fn test_bounding_box_polygon() {
+        let rect = Rect::new(0, 0, 10, 10);
+        let bbox = BoundingBox::Rectangle(SpriteRectangle::from_rect(rect));
+        assert_eq!(bbox.sides().len(), 4);
+        assert_eq!(bbox.collision_from_side(0), rect.collision_from_side(0));
+
+        let circle = BoundingBox::Circle(Circle::from_rect(&rect));
+        assert_eq!(circle.bounding_rect(), rect);
+        assert_eq!(circle.center(), (5., 5.));
+        assert_eq!(circle.collision_from_side(0), Some(CollisionSide::Bottom));
+    }
 }
@@ -16,6 +16,9 @@ pub struct Context {
     pub canvas: Canvas<sdl2::video::Window>,
     pub window: Window,
     pub score: Score,
+    /// The total time in seconds the game has been running, used as a
+    /// clock that animations can share
+    pub time: f64,
 }
 
 impl Context {
@@ -27,6 +30,7 @@ impl Context {
             events,
             canvas,
             score: Score::new(),
+            time: 0.0,
         }
     }
 }
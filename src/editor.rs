@@ -0,0 +1,336 @@
+use super::{Actor, View, ViewAction};
+use crate::actor_manager::{ActorIndex, ActorManager, ActorPosition, ActorToken};
+use crate::context::Context;
+use crate::font;
+use crate::level::{GRID_SIZE, Level};
+use crate::sprite::Sprite;
+use crate::viewport::Viewport;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io;
+
+/// The number of pixels the editor scrolls every frame
+const SCROLL_SPEED: i32 = 10;
+/// The size of the font of the status line
+const STATUS_FONT_SIZE: u16 = 24;
+
+/// The actor index given to the preview of the active token under the mouse
+/// since the preview is not in the actor manager
+const PREVIEW_INDEX: ActorIndex = ActorIndex {
+    id: usize::MAX - 1,
+    generation: 0,
+};
+
+/// Creates an actor for a token at a position in the level
+pub type ActorForToken<A> =
+    dyn Fn(ActorToken, ActorIndex, ActorPosition, &mut Canvas<Window>) -> Box<A>;
+
+/// Creates the view used to playtest a level
+pub type PlayView = dyn Fn(&Level, &mut Context) -> Box<dyn View>;
+
+/// A view for editing the main layer of a level file on the grid with the mouse
+///
+/// Controls:
+/// MOUSE_LEFT places the active token, MOUSE_RIGHT erases a token,
+/// the arrow keys scroll, TAB switches the active token in the palette,
+/// S saves the level and P starts a playtest of the level.
+/// E stops the playtest and goes back to editing.
+pub struct EditorView<A: Actor + ?Sized> {
+    path: String,
+    level: Level,
+    palette: Vec<ActorToken>,
+    active: usize,
+    actor_for_token: Box<ActorForToken<A>>,
+    play_view: Box<PlayView>,
+    /// The view of the running playtest
+    playtest: Option<Box<dyn View>>,
+    /// Preview actors for every placed token
+    actors: ActorManager<A>,
+    cells: HashMap<(usize, usize), ActorIndex>,
+    /// Preview actor for the active token under the mouse
+    cursor: Option<((usize, usize), ActorToken, Box<A>)>,
+    center: (i32, i32),
+    viewport: Viewport,
+    /// The message shown on the status line, like the result of the last save
+    status: Option<String>,
+    status_font: &'static str,
+    /// The rendered status line, recreated when the status changes
+    status_sprite: Option<Sprite>,
+}
+
+impl<A: Actor + ?Sized> EditorView<A> {
+    /// Creates an editor for the level at the path that renders
+    /// the status line with the font at the font path.
+    ///
+    /// If the level file doesn't exist, the editor starts with an empty level.
+    pub fn new(
+        path: &str,
+        palette: Vec<ActorToken>,
+        actor_for_token: Box<ActorForToken<A>>,
+        play_view: Box<PlayView>,
+        status_font: &'static str,
+        context: &mut Context,
+    ) -> io::Result<EditorView<A>> {
+        assert!(!palette.is_empty(), "The palette needs at least one token!");
+
+        let level = match Level::load(path) {
+            Ok(level) => level,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Level::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut actors = ActorManager::new();
+        let mut cells = HashMap::new();
+        for (token, position) in level.main().tokens() {
+            let next_index = actors.next_index();
+            let index = next_index.index();
+            let actor = (actor_for_token)(token, index, position, &mut context.canvas);
+            actors.add(next_index, actor);
+
+            let cell = (
+                (position.0 / GRID_SIZE) as usize,
+                (position.1 / GRID_SIZE) as usize,
+            );
+            cells.insert(cell, index);
+        }
+
+        let mut editor = EditorView {
+            path: path.to_owned(),
+            level,
+            palette,
+            active: 0,
+            actor_for_token,
+            play_view,
+            playtest: None,
+            actors,
+            cells,
+            cursor: None,
+            center: (0, 0),
+            viewport: Viewport::new(&context.window, (0, 0)),
+            status: None,
+            status_font,
+            status_sprite: None,
+        };
+        editor.update_viewport();
+
+        Ok(editor)
+    }
+
+    /// Returns the message on the status line
+    pub fn status(&self) -> Option<&str> {
+        self.status.as_deref()
+    }
+
+    /// Returns the token that gets placed on a click
+    pub fn active_token(&self) -> ActorToken {
+        self.palette[self.active]
+    }
+
+    /// Returns the level that is being edited
+    pub fn level(&self) -> &Level {
+        &self.level
+    }
+
+    /// Saves the level back to the level file
+    pub fn save(&self) -> io::Result<()> {
+        self.level.save(&self.path)
+    }
+
+    fn set_status(&mut self, status: String) {
+        self.status = Some(status);
+        self.status_sprite = None;
+    }
+
+    /// Places a token at a grid cell, or erases the cell if the token is None
+    fn set_cell(
+        &mut self,
+        cell: (usize, usize),
+        token: Option<ActorToken>,
+        canvas: &mut Canvas<Window>,
+    ) {
+        if self.level.main().get(cell.0, cell.1) == token {
+            return;
+        }
+
+        if let Some(index) = self.cells.remove(&cell) {
+            self.actors.remove(index);
+        }
+
+        self.level.main_mut().set(cell.0, cell.1, token);
+        if let Some(token) = token {
+            let position = ActorPosition(cell.0 as i32 * GRID_SIZE, cell.1 as i32 * GRID_SIZE);
+            let next_index = self.actors.next_index();
+            let index = next_index.index();
+            let actor = (self.actor_for_token)(token, index, position, canvas);
+            self.actors.add(next_index, actor);
+            self.cells.insert(cell, index);
+        }
+
+        self.update_viewport();
+    }
+
+    /// Returns the grid cell under a point in window coordinates
+    fn cell_at(&self, point: (i32, i32)) -> Option<(usize, usize)> {
+        let (x, y) = (point.0 + self.viewport.x, point.1 + self.viewport.y);
+        if x < 0 || y < 0 {
+            return None;
+        }
+
+        Some(((x / GRID_SIZE) as usize, (y / GRID_SIZE) as usize))
+    }
+
+    /// Resizes the viewport so that there is always a screen of
+    /// empty space past the level to extend it
+    fn update_viewport(&mut self) {
+        let (width, height) = self.level.main().dimensions();
+        let (window_width, window_height) = self.viewport.window_dimensions;
+        self.viewport.map_dimensions = (width + window_width, height + window_height);
+
+        let (max_x, max_y) = self.viewport.map_dimensions;
+        self.center = (
+            self.center
+                .0
+                .max(window_width / 2)
+                .min(max_x - window_width / 2),
+            self.center
+                .1
+                .max(window_height / 2)
+                .min(max_y - window_height / 2),
+        );
+        self.viewport.set_position(self.center);
+    }
+}
+
+impl<A: Actor + ?Sized> View for EditorView<A> {
+    fn render(&mut self, context: &mut Context, elapsed: f64) -> Result<(), Box<dyn Error>> {
+        if let Some(ref mut playtest) = self.playtest {
+            return playtest.render(context, elapsed);
+        }
+
+        context.canvas.set_draw_color(Color::RGB(135, 206, 250));
+        context.canvas.clear();
+
+        for actor in self.actors.values_mut() {
+            if self.viewport.rect_in_viewport(&actor.data().rect) {
+                actor.render(context, &mut self.viewport, elapsed)?;
+            }
+        }
+
+        if let Some(((col, row), _, ref mut actor)) = self.cursor {
+            actor.render(context, &mut self.viewport, elapsed)?;
+
+            let (rx, ry) = self
+                .viewport
+                .relative_point((col as i32 * GRID_SIZE, row as i32 * GRID_SIZE));
+            let rect = Rect::new(rx, ry, GRID_SIZE as u32, GRID_SIZE as u32);
+            context.canvas.set_draw_color(Color::RGB(255, 255, 255));
+            context.canvas.draw_rect(rect)?;
+        }
+
+        if let Some(ref status) = self.status {
+            if self.status_sprite.is_none() {
+                self.status_sprite = Some(font::text_sprite(
+                    &context.canvas,
+                    status,
+                    self.status_font,
+                    STATUS_FONT_SIZE,
+                    Color::RGB(255, 255, 255),
+                )?);
+            }
+            if let Some(ref sprite) = self.status_sprite {
+                font::render_text(&mut context.canvas, sprite, (10, 10))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, context: &mut Context, elapsed: f64) -> Option<ViewAction> {
+        if let Some(ref mut playtest) = self.playtest {
+            if context.events.event_called_once("E") {
+                self.playtest = None;
+                return None;
+            }
+
+            // Views that the playtest changes to, like a game over screen,
+            // are still part of the playtest
+            return match playtest.update(context, elapsed) {
+                Some(ViewAction::ChangeView(view)) => {
+                    self.playtest = Some(view);
+                    None
+                }
+                action => action,
+            };
+        }
+
+        if context.events.event_called("QUIT") || context.events.event_called("ESC") {
+            return Some(ViewAction::Quit);
+        }
+
+        if context.events.event_called_once("TAB") {
+            self.active = (self.active + 1) % self.palette.len();
+        }
+
+        if context.events.event_called_once("S") {
+            let status = match self.save() {
+                Ok(()) => format!("Saved level to {}", self.path),
+                Err(err) => format!("Could not save level to {}: {}", self.path, err),
+            };
+            self.set_status(status);
+        }
+
+        if context.events.event_called_once("P") {
+            self.playtest = Some((self.play_view)(&self.level, context));
+            return None;
+        }
+
+        // Scroll the viewport
+        if context.events.event_called("LEFT") {
+            self.center.0 -= SCROLL_SPEED;
+        }
+        if context.events.event_called("RIGHT") {
+            self.center.0 += SCROLL_SPEED;
+        }
+        if context.events.event_called("UP") {
+            self.center.1 -= SCROLL_SPEED;
+        }
+        if context.events.event_called("DOWN") {
+            self.center.1 += SCROLL_SPEED;
+        }
+        self.update_viewport();
+
+        let cell = self.cell_at(context.events.mouse_position());
+
+        if let Some(cell) = cell {
+            if context.events.event_called("MOUSE_LEFT") {
+                let token = self.active_token();
+                self.set_cell(cell, Some(token), &mut context.canvas);
+            } else if context.events.event_called("MOUSE_RIGHT") {
+                self.set_cell(cell, None, &mut context.canvas);
+            }
+        }
+
+        // Move the preview of the active token to the cell under the mouse
+        let token = self.active_token();
+        self.cursor = match (cell, self.cursor.take()) {
+            (Some(cell), Some((prev_cell, prev_token, actor)))
+                if cell == prev_cell && token == prev_token =>
+            {
+                Some((cell, token, actor))
+            }
+            (Some(cell), _) => {
+                let position = ActorPosition(cell.0 as i32 * GRID_SIZE, cell.1 as i32 * GRID_SIZE);
+                let actor =
+                    (self.actor_for_token)(token, PREVIEW_INDEX, position, &mut context.canvas);
+                Some((cell, token, actor))
+            }
+            (None, _) => None,
+        };
+
+        None
+    }
+}
@@ -40,6 +40,7 @@ where
         };
 
         game_context.events.poll();
+        game_context.time += elapsed;
 
         match curr_view.update(&mut game_context, elapsed) {
             Some(ViewAction::Quit) => break,
@@ -7,6 +7,10 @@ pub const KEYBOARD_DEFAULTS: &str = r#"
 27 ESC
 13 ENTER
 32 SPACE
+9 TAB
+101 E
+112 P
+115 S
 1073741906 UP
 1073741905 DOWN
 1073741904 LEFT
@@ -3,6 +3,7 @@ mod keyboard_mappings;
 use self::keyboard_mappings::KeyboardMappings;
 use sdl2::EventPump;
 use sdl2::event::Event;
+use sdl2::mouse::MouseButton;
 use std::collections::HashSet;
 
 /// Handles keyboard events through SDL
@@ -11,6 +12,7 @@ pub struct Events {
     events: HashSet<String>,
     once_events: HashSet<String>,
     mappings: KeyboardMappings,
+    mouse: (i32, i32),
 }
 
 impl Events {
@@ -20,6 +22,7 @@ impl Events {
             events: HashSet::new(),
             once_events: HashSet::new(),
             mappings: KeyboardMappings::from_file(mappings_path),
+            mouse: (0, 0),
         }
     }
 
@@ -55,6 +58,27 @@ impl Events {
                     self.events.remove(&action);
                     self.once_events.remove(&action);
                 }
+                Event::MouseMotion { x, y, .. } => {
+                    self.mouse = (x, y);
+                }
+                Event::MouseButtonDown {
+                    mouse_btn, x, y, ..
+                } => {
+                    self.mouse = (x, y);
+                    if let Some(action) = mouse_action(mouse_btn) {
+                        self.events.insert(action.to_owned());
+                        self.once_events.insert(action.to_owned());
+                    }
+                }
+                Event::MouseButtonUp {
+                    mouse_btn, x, y, ..
+                } => {
+                    self.mouse = (x, y);
+                    if let Some(action) = mouse_action(mouse_btn) {
+                        self.events.remove(action);
+                        self.once_events.remove(action);
+                    }
+                }
                 Event::Quit { .. } => {
                     self.events.insert("QUIT".to_owned());
                 }
@@ -78,4 +102,19 @@ impl Events {
 
         false
     }
+
+    /// Returns the last known position of the mouse in window coordinates
+    pub fn mouse_position(&self) -> (i32, i32) {
+        self.mouse
+    }
+}
+
+/// Returns the action for a mouse button
+fn mouse_action(button: MouseButton) -> Option<&'static str> {
+    match button {
+        MouseButton::Left => Some("MOUSE_LEFT"),
+        MouseButton::Right => Some("MOUSE_RIGHT"),
+        MouseButton::Middle => Some("MOUSE_MIDDLE"),
+        _ => None,
+    }
 }
@@ -1,14 +1,351 @@
-use super::Actor;
+use super::{Actor, ActorData};
 use crate::actor_manager::{ActorIndex, ActorManager, ActorPosition, ActorToken};
 use crate::context::Window;
 use crate::viewport::Viewport;
 use sdl2::render::Canvas;
-use std::fs::File;
+use std::fmt;
+use std::fs;
 use std::io;
-use std::io::{BufRead, BufReader};
 
 pub const GRID_SIZE: i32 = 40;
 
+/// The start of a line that declares a new layer in a level file like:
+/// ```text
+/// #layer background 0.5 1
+/// ```
+const LAYER_HEADER: &str = "#layer";
+
+/// A grid of actor tokens in the level file format where
+/// every character is a token and spaces are empty cells
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LevelGrid {
+    rows: Vec<Vec<Option<ActorToken>>>,
+}
+
+impl LevelGrid {
+    pub fn new() -> LevelGrid {
+        LevelGrid { rows: Vec::new() }
+    }
+
+    /// Parses a level grid from the text of a level file
+    pub fn parse(text: &str) -> LevelGrid {
+        let rows = text
+            .lines()
+            .map(|line| {
+                line.chars()
+                    .map(|token| match token {
+                        ' ' => None,
+                        token => Some(ActorToken(token)),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        LevelGrid { rows }
+    }
+
+    /// Creates a level grid from the actors in an actor manager.
+    ///
+    /// Every actor is snapped to the nearest grid cell and actors
+    /// that don't have a token are left out of the grid.
+    pub fn from_actors<A, F>(manager: &mut ActorManager<A>, token_for_actor: F) -> LevelGrid
+    where
+        A: Actor + ?Sized,
+        F: Fn(&ActorData<A::Type>) -> Option<ActorToken>,
+    {
+        let mut grid = LevelGrid::new();
+
+        for actor in manager.values_mut() {
+            let data = actor.data();
+            let token = match token_for_actor(&data) {
+                Some(token) => token,
+                None => continue,
+            };
+
+            let col = (f64::from(data.rect.x()) / f64::from(GRID_SIZE)).round();
+            let row = (f64::from(data.rect.y()) / f64::from(GRID_SIZE)).round();
+            if col >= 0. && row >= 0. {
+                grid.set(col as usize, row as usize, Some(token));
+            }
+        }
+
+        grid
+    }
+
+    /// Returns the token at a grid cell
+    pub fn get(&self, col: usize, row: usize) -> Option<ActorToken> {
+        self.rows
+            .get(row)
+            .and_then(|tokens| tokens.get(col))
+            .cloned()
+            .unwrap_or(None)
+    }
+
+    /// Sets or clears the token at a grid cell, growing the grid if needed
+    pub fn set(&mut self, col: usize, row: usize, token: Option<ActorToken>) {
+        if token.is_none() && self.get(col, row).is_none() {
+            return;
+        }
+
+        if row >= self.rows.len() {
+            self.rows.resize(row + 1, Vec::new());
+        }
+        let tokens = &mut self.rows[row];
+        if col >= tokens.len() {
+            tokens.resize(col + 1, None);
+        }
+        tokens[col] = token;
+    }
+
+    /// Clears every cell with a token that doesn't satisfy the predicate
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: Fn(ActorToken) -> bool,
+    {
+        for token in self.rows.iter_mut().flat_map(|tokens| tokens.iter_mut()) {
+            if token.is_some_and(|token| !f(token)) {
+                *token = None;
+            }
+        }
+    }
+
+    /// Returns the number of columns in the widest row
+    pub fn columns(&self) -> usize {
+        self.rows.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Returns the number of rows
+    pub fn rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns the width and height of the grid in pixels
+    pub fn dimensions(&self) -> (i32, i32) {
+        (
+            self.columns() as i32 * GRID_SIZE,
+            self.rows() as i32 * GRID_SIZE,
+        )
+    }
+
+    /// Returns all of the tokens in the grid with their positions
+    pub fn tokens(&self) -> impl Iterator<Item = (ActorToken, ActorPosition)> + '_ {
+        self.rows.iter().enumerate().flat_map(|(row, tokens)| {
+            tokens.iter().enumerate().filter_map(move |(col, token)| {
+                token.map(|token| {
+                    let position = ActorPosition(col as i32 * GRID_SIZE, row as i32 * GRID_SIZE);
+                    (token, position)
+                })
+            })
+        })
+    }
+}
+
+impl fmt::Display for LevelGrid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for tokens in &self.rows {
+            let line: String = tokens
+                .iter()
+                .map(|token| token.map_or(' ', |ActorToken(token)| token))
+                .collect();
+            writeln!(f, "{}", line.trim_end())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Where a layer is drawn relative to the actors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayerKind {
+    /// Drawn behind the actors
+    Background,
+    /// The layer that contains the actors
+    Main,
+    /// Drawn over the actors
+    Foreground,
+}
+
+impl LayerKind {
+    fn name(self) -> &'static str {
+        match self {
+            LayerKind::Background => "background",
+            LayerKind::Main => "main",
+            LayerKind::Foreground => "foreground",
+        }
+    }
+}
+
+/// A layer of a level with its own grid of tokens
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelLayer {
+    pub kind: LayerKind,
+    /// How far the layer scrolls in the x and y axis relative to the viewport,
+    /// where 1.0 scrolls with the actors and 0.0 doesn't scroll at all
+    pub scroll: (f64, f64),
+    pub grid: LevelGrid,
+}
+
+impl LevelLayer {
+    pub fn new(kind: LayerKind, scroll: (f64, f64)) -> LevelLayer {
+        LevelLayer {
+            kind,
+            scroll,
+            grid: LevelGrid::new(),
+        }
+    }
+
+    /// Parses a layer header line like "#layer background 0.5 1"
+    fn parse_header(line: &str) -> io::Result<LevelLayer> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid layer header: {}", line),
+            )
+        };
+
+        let mut words = line.split_whitespace().skip(1);
+        let kind = match words.next() {
+            Some("background") => LayerKind::Background,
+            Some("main") => LayerKind::Main,
+            Some("foreground") => LayerKind::Foreground,
+            _ => return Err(invalid()),
+        };
+        let mut factor = || -> io::Result<f64> {
+            words
+                .next()
+                .map_or(Ok(1.0), str::parse)
+                .map_err(|_| invalid())
+        };
+        let scroll = (factor()?, factor()?);
+
+        Ok(LevelLayer::new(kind, scroll))
+    }
+}
+
+/// A level made up of layers where the main layer contains the actors and
+/// the background and foreground layers contain decorations.
+///
+/// Rows before the first layer header belong to the main layer so
+/// level files without headers have a single main layer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Level {
+    pub layers: Vec<LevelLayer>,
+}
+
+impl Default for Level {
+    fn default() -> Level {
+        Level::new()
+    }
+}
+
+impl Level {
+    /// Creates a level with an empty main layer
+    pub fn new() -> Level {
+        Level {
+            layers: vec![LevelLayer::new(LayerKind::Main, (1.0, 1.0))],
+        }
+    }
+
+    /// Parses a level from the text of a level file.
+    /// Levels can only have one main layer.
+    pub fn parse(text: &str) -> io::Result<Level> {
+        let mut layers = Vec::new();
+        let mut current = LevelLayer::new(LayerKind::Main, (1.0, 1.0));
+        let mut lines = Vec::new();
+
+        for line in text.lines() {
+            if line.starts_with(LAYER_HEADER) {
+                // Skip the implicit main layer if there are no rows before the first header
+                if !layers.is_empty() || !lines.is_empty() {
+                    current.grid = LevelGrid::parse(&lines.join("\n"));
+                    layers.push(current);
+                }
+                current = LevelLayer::parse_header(line)?;
+                lines.clear();
+            } else {
+                lines.push(line);
+            }
+        }
+        current.grid = LevelGrid::parse(&lines.join("\n"));
+        layers.push(current);
+
+        let main_layers = layers
+            .iter()
+            .filter(|layer| layer.kind == LayerKind::Main)
+            .count();
+        if main_layers > 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "A level can only have one main layer",
+            ));
+        }
+        if main_layers == 0 {
+            layers.push(LevelLayer::new(LayerKind::Main, (1.0, 1.0)));
+        }
+
+        Ok(Level { layers })
+    }
+
+    /// Loads a level from a level file
+    pub fn load(path: &str) -> io::Result<Level> {
+        fs::read_to_string(path).and_then(|text| Level::parse(&text))
+    }
+
+    /// Writes the level to a level file
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// Returns the grid of the main layer that contains the actors
+    pub fn main(&self) -> &LevelGrid {
+        &self.main_layer().grid
+    }
+
+    /// Returns a mutable reference to the grid of the main layer
+    pub fn main_mut(&mut self) -> &mut LevelGrid {
+        let index = self.main_index();
+        &mut self.layers[index].grid
+    }
+
+    /// Returns the layers of a kind in drawing order
+    pub fn layers(&self, kind: LayerKind) -> impl Iterator<Item = &LevelLayer> {
+        self.layers.iter().filter(move |layer| layer.kind == kind)
+    }
+
+    fn main_layer(&self) -> &LevelLayer {
+        &self.layers[self.main_index()]
+    }
+
+    fn main_index(&self) -> usize {
+        self.layers
+            .iter()
+            .position(|layer| layer.kind == LayerKind::Main)
+            .expect("Level needs to have a main layer")
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, layer) in self.layers.iter().enumerate() {
+            // The first main layer doesn't need a header if it scrolls with the viewport
+            let implicit = i == 0 && layer.kind == LayerKind::Main && layer.scroll == (1.0, 1.0);
+            if !implicit {
+                writeln!(
+                    f,
+                    "{} {} {} {}",
+                    LAYER_HEADER,
+                    layer.kind.name(),
+                    layer.scroll.0,
+                    layer.scroll.1
+                )?;
+            }
+            write!(f, "{}", layer.grid)?;
+        }
+
+        Ok(())
+    }
+}
+
 /// Loads a new level and returns an ActorManager with the loaded actors
 pub fn load_level<A, F>(
     path: &str,
@@ -20,55 +357,162 @@ where
     A: Actor + ?Sized,
     F: Fn(ActorToken, ActorIndex, ActorPosition, &mut Canvas<sdl2::video::Window>) -> Box<A>,
 {
-    let mut center_point = (0, 0);
+    let level = Level::load(path)?;
+    load_grid(level.main(), actor_for_token, canvas, window)
+        .map_err(|err| io::Error::other(format!("{}: {}", path, err)))
+}
+
+/// Creates the actors in a level grid and returns an ActorManager with
+/// the loaded actors and a viewport centered on the player
+pub fn load_grid<A, F>(
+    grid: &LevelGrid,
+    actor_for_token: F,
+    canvas: &mut Canvas<sdl2::video::Window>,
+    window: &Window,
+) -> io::Result<(ActorManager<A>, Viewport)>
+where
+    A: Actor + ?Sized,
+    F: Fn(ActorToken, ActorIndex, ActorPosition, &mut Canvas<sdl2::video::Window>) -> Box<A>,
+{
+    let viewport = grid_viewport(grid, window)?;
     let mut manager = ActorManager::new();
 
-    File::open(path).and_then(|file| {
-        let reader = BufReader::new(file);
-
-        let mut x = 0;
-        let mut y = 0;
-        let mut width = 0;
-        let mut has_player = false;
-
-        for line in reader.lines() {
-            for token in line?.chars() {
-                if token != ' ' {
-                    let next_index = manager.next_index();
-                    let actor = actor_for_token(
-                        ActorToken(token),
-                        next_index.index(),
-                        ActorPosition(x, y),
-                        canvas,
-                    );
-                    manager.add(next_index, actor);
-
-                    if token == 'P' {
-                        has_player = true;
-                        center_point = (x, y);
-                    }
-                }
+    for (token, position) in grid.tokens() {
+        let next_index = manager.next_index();
+        let actor = actor_for_token(token, next_index.index(), position, canvas);
+        manager.add(next_index, actor);
+    }
 
-                x += GRID_SIZE;
-            }
+    Ok((manager, viewport))
+}
 
-            width = x;
-            x = 0;
-            y += GRID_SIZE;
-        }
+/// Returns a viewport for a level grid centered on the player
+pub fn grid_viewport(grid: &LevelGrid, window: &Window) -> io::Result<Viewport> {
+    let ActorPosition(x, y) = grid
+        .tokens()
+        .find(|&(token, _)| token == ActorToken('P'))
+        .map(|(_, position)| position)
+        .ok_or_else(|| io::Error::other("Level needs to have a player"))?;
 
-        let (width, height) = (width, y);
+    let mut viewport = Viewport::new(window, grid.dimensions());
+    viewport.set_position((x, y));
 
-        if !has_player {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Level at {} needs to have a player", path),
-            ));
-        }
+    Ok(viewport)
+}
+
+/// Saves the actors in an ActorManager to the main layer of a level file
+/// and keeps the other layers if the file already exists
+pub fn save_level<A, F>(
+    path: &str,
+    manager: &mut ActorManager<A>,
+    token_for_actor: F,
+) -> io::Result<()>
+where
+    A: Actor + ?Sized,
+    F: Fn(&ActorData<A::Type>) -> Option<ActorToken>,
+{
+    let mut level = match Level::load(path) {
+        Ok(level) => level,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Level::new(),
+        Err(err) => return Err(err),
+    };
+
+    *level.main_mut() = LevelGrid::from_actors(manager, token_for_actor);
+    level.save(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format() {
+        let text = "  P\n==  C\n--===\n";
+        let grid = LevelGrid::parse(text);
+
+        assert_eq!(grid.rows(), 3);
+        assert_eq!(grid.columns(), 5);
+        assert_eq!(grid.get(2, 0), Some(ActorToken('P')));
+        assert_eq!(grid.get(0, 0), None);
+        assert_eq!(grid.get(10, 10), None);
+        assert_eq!(grid.to_string(), text);
+    }
+
+    #[test]
+    fn test_set_grows_grid() {
+        let mut grid = LevelGrid::new();
+        grid.set(3, 1, Some(ActorToken('C')));
+
+        assert_eq!(grid.rows(), 2);
+        assert_eq!(grid.columns(), 4);
+        assert_eq!(grid.dimensions(), (4 * GRID_SIZE, 2 * GRID_SIZE));
+        assert_eq!(grid.to_string(), "\n   C\n");
+
+        grid.set(3, 1, None);
+        assert_eq!(grid.get(3, 1), None);
+        grid.set(10, 10, None);
+        assert_eq!(grid.rows(), 2);
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut grid = LevelGrid::parse("P==\n-C-");
+        grid.retain(|token| token != ActorToken('=') && token != ActorToken('-'));
+
+        assert_eq!(grid, LevelGrid::parse("P  \n C "));
+    }
+
+    #[test]
+    fn test_parse_level_without_layers() {
+        let text = "  P\n==  C\n";
+        let level = Level::parse(text).unwrap();
+
+        assert_eq!(level.layers.len(), 1);
+        assert_eq!(level.layers[0].kind, LayerKind::Main);
+        assert_eq!(level.main(), &LevelGrid::parse(text));
+        assert_eq!(level.to_string(), text);
+    }
+
+    #[test]
+    fn test_parse_level_layers() {
+        let text =
+            "#layer background 0.5 1\n| |\n#layer main 1 1\n P\n==\n#layer foreground 1.5 1\n~~\n";
+        let level = Level::parse(text).unwrap();
+
+        assert_eq!(level.layers.len(), 3);
+        assert_eq!(level.layers[0].kind, LayerKind::Background);
+        assert_eq!(level.layers[0].scroll, (0.5, 1.0));
+        assert_eq!(level.layers[0].grid, LevelGrid::parse("| |"));
+        assert_eq!(level.main(), &LevelGrid::parse(" P\n=="));
+        assert_eq!(level.layers(LayerKind::Foreground).count(), 1);
+        assert_eq!(level.to_string(), text);
+    }
+
+    #[test]
+    fn test_parse_level_errors() {
+        assert!(Level::parse("#layer sideways 1 1\n").is_err());
+        assert!(Level::parse("#layer background fast\n").is_err());
+        assert!(Level::parse("#layer main\n==\n#layer main\n==\n").is_err());
+        // Rows before the first header are an implicit main layer
+        assert!(Level::parse("==\n#layer main\n==\n").is_err());
+
+        // A main layer is added if the level doesn't declare one
+        let level = Level::parse("#layer background\n||\n").unwrap();
+        assert_eq!(level.layers[0].scroll, (1.0, 1.0));
+        assert_eq!(level.main(), &LevelGrid::new());
+    }
 
-        let mut viewport = Viewport::new(window, (width, height));
-        viewport.set_position(center_point);
+    #[test]
+    fn test_tokens() {
+        let grid = LevelGrid::parse("P \n =");
+        let tokens = grid.tokens().collect::<Vec<_>>();
 
-        Ok((manager, viewport))
-    })
+        assert_eq!(
+            tokens,
+            vec![
+                (ActorToken('P'), ActorPosition(0, 0)),
+                (ActorToken('='), ActorPosition(GRID_SIZE, GRID_SIZE)),
+            ]
+        );
+    }
 }
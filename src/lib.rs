@@ -16,34 +16,66 @@
 //! change in the x axis, and right is a positive change in the x axis.
 
 pub mod actor_manager;
+pub mod autotile;
 pub mod block;
 pub mod cache;
 pub mod collision;
 pub mod context;
+pub mod editor;
 pub mod event_loop;
 pub mod events;
 pub mod font;
 pub mod level;
+pub mod parallax;
+pub mod pathfinding;
 pub mod quadtree;
 pub mod raycast;
 pub mod score;
+pub mod shape;
+pub mod spatial;
+pub mod spatial_hash;
 pub mod sprite;
+pub mod steering;
+pub mod streaming;
+pub mod tilemap;
+pub mod trigger;
 pub mod vector;
 pub mod viewport;
+pub mod visibility;
+pub mod watcher;
+pub mod world;
+
+#[cfg(test)]
+mod test_utils;
 
 pub use crate::actor_manager::{ActorIndex, ActorManager, ActorPosition, ActorToken};
-pub use crate::collision::{BoundingBox, Collision, CollisionSide};
+pub use crate::autotile::{AutotileRules, Neighborhood};
+pub use crate::collision::{
+    BoundingBox, Collision, CollisionLayers, CollisionSide, Contact, TileShape,
+};
 pub use crate::context::{Context, Window};
+pub use crate::editor::EditorView;
 pub use crate::events::Events;
+pub use crate::parallax::ParallaxLayer;
+pub use crate::pathfinding::{PathCell, PathMode, Pathfinder};
 pub use crate::quadtree::Quadtree;
-pub use crate::raycast::{Polygon, Segment};
+pub use crate::raycast::{Polygon, RayHit, Segment, SweepHit};
 pub use crate::score::Score;
+pub use crate::shape::{Circle, ConvexPolygon};
+pub use crate::spatial::{Broadphase, QueryFilter, SpatialIndex};
+pub use crate::spatial_hash::SpatialHash;
 pub use crate::sprite::{
     AnimatedSprite, Animations, Direction, Renderable, Sprite, SpriteRectangle, Spritesheet,
     SpritesheetConfig,
 };
+pub use crate::steering::{Patrol, Steering};
+pub use crate::streaming::LevelStreamer;
+pub use crate::tilemap::{TileType, Tilemap};
+pub use crate::trigger::{TriggerEvent, TriggerTracker};
 pub use crate::vector::{PositionChange, Vector2D};
 pub use crate::viewport::Viewport;
+pub use crate::watcher::FileWatcher;
+pub use crate::world::{CollisionPair, PairEvent, RaycastHit, ShapeCastHit, World};
 
 use sdl2::rect::Rect;
 use std::error::Error;
@@ -87,6 +119,13 @@ pub struct ActorData<Type> {
     pub rect: Rect,
     /// The current bounding box for the actor
     pub bounding_box: Option<BoundingBox>,
+    /// The shape that other actors stand on when they collide with the actor
+    pub shape: TileShape,
+    /// The collision layers the actor is on and collides with
+    pub layers: CollisionLayers,
+    /// If true, the actor is a trigger volume that other actors pass through
+    /// and that gets trigger events instead of collisions
+    pub trigger: bool,
     /// The type of the actor
     pub actor_type: Type,
 }
@@ -0,0 +1,83 @@
+use crate::actor_manager::ActorToken;
+use crate::collision::{CollisionLayers, TileShape};
+use crate::level::{LayerKind, Level, LevelLayer};
+use crate::sprite::Sprite;
+use crate::tilemap::{TileType, Tilemap};
+use crate::viewport::Viewport;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use std::error::Error;
+
+/// Renders the tokens of a background or foreground level layer
+/// as tiles that scroll relative to the viewport
+pub struct ParallaxLayer {
+    kind: LayerKind,
+    scroll: (f64, f64),
+    tilemap: Tilemap<()>,
+}
+
+impl ParallaxLayer {
+    /// Creates a parallax layer from a level layer given a function
+    /// that returns the sprite for a token
+    pub fn new<F>(
+        layer: &LevelLayer,
+        sprite_for_token: F,
+        canvas: &mut Canvas<Window>,
+    ) -> ParallaxLayer
+    where
+        F: Fn(ActorToken, &mut Canvas<Window>) -> Option<Sprite>,
+    {
+        let tile_for_token = |token, canvas: &mut Canvas<Window>| {
+            sprite_for_token(token, canvas).map(|sprite| TileType {
+                sprite: Some(sprite),
+                animation: None,
+                collision_filter: 0,
+                shape: TileShape::Solid,
+                layers: CollisionLayers::default(),
+                actor_type: (),
+            })
+        };
+
+        ParallaxLayer {
+            kind: layer.kind,
+            scroll: layer.scroll,
+            tilemap: Tilemap::from_grid(&layer.grid, tile_for_token, canvas),
+        }
+    }
+
+    /// Returns whether the layer is drawn behind or over the actors
+    pub fn kind(&self) -> LayerKind {
+        self.kind
+    }
+
+    /// Sets the time of the animated tiles of the layer to the game clock
+    pub fn set_time(&mut self, time: f64) {
+        self.tilemap.set_time(time);
+    }
+
+    /// Renders the tiles of the layer that are inside the viewport
+    pub fn render(
+        &self,
+        viewport: &Viewport,
+        canvas: &mut Canvas<Window>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.tilemap.render_parallax(viewport, self.scroll, canvas)
+    }
+}
+
+/// Creates parallax layers for all of the background and foreground layers in a level
+pub fn parallax_layers<F>(
+    level: &Level,
+    sprite_for_token: F,
+    canvas: &mut Canvas<Window>,
+) -> Vec<ParallaxLayer>
+where
+    F: Fn(ActorToken, &mut Canvas<Window>) -> Option<Sprite>,
+{
+    level
+        .layers
+        .iter()
+        .filter(|layer| layer.kind != LayerKind::Main)
+        .map(|layer| ParallaxLayer::new(layer, &sprite_for_token, canvas))
+        .collect()
+}
@@ -0,0 +1,502 @@
+use crate::actor_manager::ActorToken;
+use crate::collision::TileShape;
+use crate::level::{GRID_SIZE, LevelGrid};
+use crate::tilemap::Tilemap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+/// The cost of moving straight to a neighboring cell in top-down mode
+const STRAIGHT_COST: i32 = 10;
+/// The cost of moving diagonally to a neighboring cell in top-down mode
+const DIAGONAL_COST: i32 = 14;
+
+/// What a grid cell is to the pathfinder
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathCell {
+    /// Actors can move through the cell
+    Empty,
+    /// Actors can't move through the cell but can stand on it
+    Solid,
+    /// Actors can move through the cell and stand on it,
+    /// like one-way platforms and slopes
+    Platform,
+}
+
+/// How actors move from cell to cell
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathMode {
+    /// Actors move in eight directions through the empty cells
+    /// without cutting the corners of solid cells
+    TopDown,
+    /// Actors walk on top of solid cells and platforms, jump and fall
+    Platformer {
+        /// The number of cells that the actor is tall
+        height: i32,
+        /// The number of cells that the actor can jump up
+        jump_height: i32,
+        /// The number of cells that the actor can jump across
+        jump_distance: i32,
+        /// The number of cells that the actor can fall down
+        max_fall: i32,
+    },
+}
+
+/// Finds paths through a grid of cells with A*, like the tiles of a level.
+///
+/// Paths are lists of grid cells from the start to the goal
+/// or the points in the world at the center of the cells.
+pub struct Pathfinder {
+    columns: usize,
+    rows: usize,
+    cells: Vec<PathCell>,
+    mode: PathMode,
+}
+
+impl Pathfinder {
+    /// Creates a pathfinder for a grid with only empty cells
+    pub fn new(columns: usize, rows: usize, mode: PathMode) -> Pathfinder {
+        Pathfinder {
+            columns,
+            rows,
+            cells: vec![PathCell::Empty; columns * rows],
+            mode,
+        }
+    }
+
+    /// Creates a pathfinder for the tiles of a tilemap
+    pub fn from_tilemap<Type>(tilemap: &Tilemap<Type>, mode: PathMode) -> Pathfinder {
+        let mut pathfinder = Pathfinder::new(tilemap.columns(), tilemap.rows(), mode);
+        for row in 0..tilemap.rows() {
+            for col in 0..tilemap.columns() {
+                let cell = match tilemap.get(col, row) {
+                    Some(tile) if tile.collision_filter != 0 => match tile.shape {
+                        TileShape::Solid => PathCell::Solid,
+                        TileShape::OneWay | TileShape::Slope { .. } => PathCell::Platform,
+                    },
+                    _ => PathCell::Empty,
+                };
+                pathfinder.set(col, row, cell);
+            }
+        }
+        pathfinder
+    }
+
+    /// Creates a pathfinder for a level grid with a function
+    /// that returns what the cell of every token is
+    pub fn from_grid<F>(grid: &LevelGrid, cell_for_token: F, mode: PathMode) -> Pathfinder
+    where
+        F: Fn(ActorToken) -> PathCell,
+    {
+        let mut pathfinder = Pathfinder::new(grid.columns(), grid.rows(), mode);
+        for row in 0..grid.rows() {
+            for col in 0..grid.columns() {
+                if let Some(token) = grid.get(col, row) {
+                    pathfinder.set(col, row, cell_for_token(token));
+                }
+            }
+        }
+        pathfinder
+    }
+
+    /// Returns the cell at a column and row, or None if it is outside of the grid
+    pub fn get(&self, col: usize, row: usize) -> Option<PathCell> {
+        if col >= self.columns || row >= self.rows {
+            return None;
+        }
+        Some(self.cells[row * self.columns + col])
+    }
+
+    /// Changes a cell, like when a block is destroyed.
+    /// Cells outside of the grid are ignored.
+    pub fn set(&mut self, col: usize, row: usize, cell: PathCell) {
+        if col < self.columns && row < self.rows {
+            self.cells[row * self.columns + col] = cell;
+        }
+    }
+
+    pub fn mode(&self) -> PathMode {
+        self.mode
+    }
+
+    /// Returns the cells of the cheapest path from a start cell to a goal cell
+    /// including both of them, or None if the goal can't be reached
+    pub fn find_path(
+        &self,
+        start: (usize, usize),
+        goal: (usize, usize),
+    ) -> Option<Vec<(usize, usize)>> {
+        let start = (start.0 as i32, start.1 as i32);
+        let goal = (goal.0 as i32, goal.1 as i32);
+        if !self.can_stand(start) || !self.can_stand(goal) {
+            return None;
+        }
+
+        let mut open = BinaryHeap::new();
+        let mut costs = HashMap::new();
+        let mut came_from = HashMap::new();
+        open.push(Reverse((self.heuristic(start, goal), 0, start)));
+        costs.insert(start, 0);
+
+        while let Some(Reverse((_, cost, cell))) = open.pop() {
+            if cell == goal {
+                let mut path = vec![cell];
+                let mut cell = cell;
+                while let Some(&prev) = came_from.get(&cell) {
+                    path.push(prev);
+                    cell = prev;
+                }
+                path.reverse();
+                return Some(
+                    path.into_iter()
+                        .map(|(c, r)| (c as usize, r as usize))
+                        .collect(),
+                );
+            }
+            if costs.get(&cell).is_some_and(|&best| cost > best) {
+                continue;
+            }
+
+            for (next, step_cost) in self.neighbors(cell) {
+                let next_cost = cost + step_cost;
+                if costs.get(&next).is_none_or(|&best| next_cost < best) {
+                    costs.insert(next, next_cost);
+                    came_from.insert(next, cell);
+                    let estimate = next_cost + self.heuristic(next, goal);
+                    open.push(Reverse((estimate, next_cost, next)));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns the points in the world at the centers of the cells of the cheapest
+    /// path between the cells that contain two points, or None if there is no path
+    pub fn find_world_path(&self, start: (i32, i32), goal: (i32, i32)) -> Option<Vec<(i32, i32)>> {
+        let cell = |(x, y): (i32, i32)| {
+            let (col, row) = (x.div_euclid(GRID_SIZE), y.div_euclid(GRID_SIZE));
+            (col >= 0 && row >= 0).then_some((col as usize, row as usize))
+        };
+        let path = self.find_path(cell(start)?, cell(goal)?)?;
+
+        Some(
+            path.into_iter()
+                .map(|(col, row)| {
+                    (
+                        col as i32 * GRID_SIZE + GRID_SIZE / 2,
+                        row as i32 * GRID_SIZE + GRID_SIZE / 2,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns true if actors can't move through a cell.
+    /// The cells outside of the grid block actors.
+    fn blocked(&self, (col, row): (i32, i32)) -> bool {
+        if col < 0 || row < 0 {
+            return true;
+        }
+        self.get(col as usize, row as usize)
+            .is_none_or(|cell| cell == PathCell::Solid)
+    }
+
+    /// Returns true if actors can stand on top of a cell
+    fn floor(&self, (col, row): (i32, i32)) -> bool {
+        col >= 0
+            && row >= 0
+            && self
+                .get(col as usize, row as usize)
+                .is_some_and(|cell| cell != PathCell::Empty)
+    }
+
+    /// Returns true if the actor fits with its feet in a cell
+    fn fits(&self, (col, row): (i32, i32)) -> bool {
+        match self.mode {
+            PathMode::TopDown => !self.blocked((col, row)),
+            PathMode::Platformer { height, .. } => {
+                (0..height.max(1)).all(|up| !self.blocked((col, row - up)))
+            }
+        }
+    }
+
+    /// Returns true if the actor can be in a cell at the end of a move
+    fn can_stand(&self, (col, row): (i32, i32)) -> bool {
+        match self.mode {
+            PathMode::TopDown => self.fits((col, row)),
+            PathMode::Platformer { .. } => self.fits((col, row)) && self.floor((col, row + 1)),
+        }
+    }
+
+    /// A lower bound of the cost from a cell to the goal
+    fn heuristic(&self, (col, row): (i32, i32), (goal_col, goal_row): (i32, i32)) -> i32 {
+        let (dx, dy) = ((goal_col - col).abs(), (goal_row - row).abs());
+        match self.mode {
+            PathMode::TopDown => {
+                STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+            }
+            PathMode::Platformer { .. } => dx.max(dy),
+        }
+    }
+
+    /// Returns the cells that the actor can move to from a cell with their costs
+    fn neighbors(&self, cell: (i32, i32)) -> Vec<((i32, i32), i32)> {
+        match self.mode {
+            PathMode::TopDown => self.top_down_neighbors(cell),
+            PathMode::Platformer {
+                jump_height,
+                jump_distance,
+                max_fall,
+                ..
+            } => self.platformer_neighbors(cell, jump_height, jump_distance, max_fall),
+        }
+    }
+
+    fn top_down_neighbors(&self, (col, row): (i32, i32)) -> Vec<((i32, i32), i32)> {
+        let mut neighbors = Vec::with_capacity(8);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if (dx, dy) == (0, 0) || !self.fits((col + dx, row + dy)) {
+                    continue;
+                }
+
+                if dx != 0 && dy != 0 {
+                    // don't cut the corners of solid cells
+                    if !self.fits((col + dx, row)) || !self.fits((col, row + dy)) {
+                        continue;
+                    }
+                    neighbors.push(((col + dx, row + dy), DIAGONAL_COST));
+                } else {
+                    neighbors.push(((col + dx, row + dy), STRAIGHT_COST));
+                }
+            }
+        }
+        neighbors
+    }
+
+    fn platformer_neighbors(
+        &self,
+        (col, row): (i32, i32),
+        jump_height: i32,
+        jump_distance: i32,
+        max_fall: i32,
+    ) -> Vec<((i32, i32), i32)> {
+        let mut neighbors = Vec::new();
+        for dir in [-1, 1] {
+            let side = (col + dir, row);
+
+            // walk to the next cell or walk off of the ledge and fall
+            if self.can_stand(side) {
+                neighbors.push((side, 1));
+            } else if self.fits(side) {
+                let landing = (1..=max_fall)
+                    .take_while(|&fall| self.fits((side.0, row + fall)))
+                    .find(|&fall| self.can_stand((side.0, row + fall)));
+                if let Some(fall) = landing {
+                    neighbors.push(((side.0, row + fall), 1 + fall));
+                }
+            }
+
+            // jump up and over to any cell that can be stood on
+            for distance in 1..=jump_distance {
+                let target_col = col + dir * distance;
+                for target_row in row - jump_height..=row + max_fall {
+                    let target = (target_col, target_row);
+                    if !self.can_stand(target) {
+                        continue;
+                    }
+                    if let Some(cost) = self.jump_cost((col, row), target, jump_height) {
+                        neighbors.push((target, cost));
+                    }
+                }
+            }
+        }
+        neighbors
+    }
+
+    /// Returns the cost of jumping between two cells, or None if the jump
+    /// is too high or something blocks the arc. The arc goes straight up
+    /// to one cell above the higher cell, across and then straight down.
+    fn jump_cost(&self, from: (i32, i32), to: (i32, i32), jump_height: i32) -> Option<i32> {
+        let apex = from.1.min(to.1) - 1;
+        if from.1 - apex > jump_height {
+            return None;
+        }
+
+        let dir = (to.0 - from.0).signum();
+        let up = (apex..from.1).all(|row| self.fits((from.0, row)));
+        let across = (1..=(to.0 - from.0).abs()).all(|i| self.fits((from.0 + dir * i, apex)));
+        let down = (apex..=to.1).all(|row| self.fits((to.0, row)));
+
+        (up && across && down).then(|| (to.0 - from.0).abs() + (from.1 - apex) + (to.1 - apex))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a pathfinder from rows of '#' for solid cells,
+    /// '=' for platforms and spaces for empty cells
+    fn parse(rows: &[&str], mode: PathMode) -> Pathfinder {
+        let grid = LevelGrid::parse(&rows.join("\n"));
+        Pathfinder::from_grid(
+            &grid,
+            |ActorToken(token)| match token {
+                '#' => PathCell::Solid,
+                '=' => PathCell::Platform,
+                _ => PathCell::Empty,
+            },
+            mode,
+        )
+    }
+
+    const PLATFORMER: PathMode = PathMode::Platformer {
+        height: 1,
+        jump_height: 2,
+        jump_distance: 3,
+        max_fall: 4,
+    };
+
+    #[test]
+    fn test_top_down() {
+        let pathfinder = parse(
+            &[
+                "     ", //
+                " ### ", //
+                "   # ", //
+                " # # ", //
+                " #   ", //
+            ],
+            PathMode::TopDown,
+        );
+
+        let path = pathfinder.find_path((0, 4), (4, 4)).unwrap();
+        assert_eq!(path.first(), Some(&(0, 4)));
+        assert_eq!(path.last(), Some(&(4, 4)));
+        for pair in path.windows(2) {
+            let (dx, dy) = (
+                pair[0].0 as i32 - pair[1].0 as i32,
+                pair[0].1 as i32 - pair[1].1 as i32,
+            );
+            assert!(dx.abs() <= 1 && dy.abs() <= 1);
+            assert_eq!(pathfinder.get(pair[1].0, pair[1].1), Some(PathCell::Empty));
+        }
+
+        // Diagonal moves don't cut between two solid cells
+        let pathfinder = parse(&["  #", " # ", "   "], PathMode::TopDown);
+        let path = pathfinder.find_path((0, 0), (2, 1)).unwrap();
+        assert_eq!(path.len(), 6);
+
+        let walled = parse(&[" # ", " # ", " # "], PathMode::TopDown);
+        assert_eq!(walled.find_path((0, 0), (2, 2)), None);
+        assert_eq!(walled.find_path((0, 0), (1, 1)), None);
+    }
+
+    #[test]
+    fn test_platformer_walk_and_jump() {
+        let pathfinder = parse(
+            &[
+                "          ", //
+                "          ", //
+                "       ===", //
+                "    #     ", //
+                "  ###     ", //
+                "##########", //
+            ],
+            PLATFORMER,
+        );
+
+        // Walk, jump up the steps and jump onto the platform
+        let path = pathfinder.find_path((0, 4), (9, 1)).unwrap();
+        assert_eq!(path.first(), Some(&(0, 4)));
+        assert_eq!(path.last(), Some(&(9, 1)));
+        assert!(
+            path.iter()
+                .all(|&(col, row)| pathfinder.can_stand((col as i32, row as i32)))
+        );
+
+        // The top of the platform can't be reached from a cell in the air
+        assert_eq!(pathfinder.find_path((0, 1), (9, 1)), None);
+    }
+
+    #[test]
+    fn test_platformer_limits() {
+        let rows = [
+            "     ", //
+            "     ", //
+            "    #", //
+            "    #", //
+            "    #", //
+            "#####", //
+        ];
+        let short = parse(&rows, PLATFORMER);
+        assert_eq!(short.find_path((0, 4), (4, 1)), None);
+
+        let high = parse(
+            &rows,
+            PathMode::Platformer {
+                height: 1,
+                jump_height: 4,
+                jump_distance: 3,
+                max_fall: 4,
+            },
+        );
+        assert!(high.find_path((0, 4), (4, 1)).is_some());
+
+        // Falling down the wall but not further than the max fall
+        assert!(high.find_path((4, 1), (3, 4)).is_some());
+        let shallow = parse(
+            &rows,
+            PathMode::Platformer {
+                height: 1,
+                jump_height: 4,
+                jump_distance: 0,
+                max_fall: 1,
+            },
+        );
+        assert_eq!(shallow.find_path((4, 1), (3, 4)), None);
+    }
+
+    #[test]
+    fn test_tall_actors() {
+        // A tall actor can't walk under the low ceiling
+        let rows = [
+            "      ", //
+            "  ##  ", //
+            "      ", //
+            "######", //
+        ];
+        let short = parse(&rows, PLATFORMER);
+        assert!(short.find_path((0, 2), (5, 2)).is_some());
+
+        let tall = parse(
+            &rows,
+            PathMode::Platformer {
+                height: 2,
+                jump_height: 0,
+                jump_distance: 0,
+                max_fall: 4,
+            },
+        );
+        assert_eq!(tall.find_path((0, 2), (5, 2)), None);
+    }
+
+    #[test]
+    fn test_world_path() {
+        let pathfinder = parse(&["   ", "###"], PLATFORMER);
+        let path = pathfinder
+            .find_world_path((5, 10), (2 * GRID_SIZE + 5, 10))
+            .unwrap();
+        let half = GRID_SIZE / 2;
+        assert_eq!(
+            path,
+            vec![
+                (half, half),
+                (GRID_SIZE + half, half),
+                (2 * GRID_SIZE + half, half)
+            ]
+        );
+        assert_eq!(pathfinder.find_world_path((-5, 10), (5, 10)), None);
+    }
+}
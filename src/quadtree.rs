@@ -1,137 +1,263 @@
 use super::ActorData;
-use crate::viewport::Viewport;
+use crate::actor_manager::ActorIndex;
+use crate::spatial::{self, QueryFilter, SpatialIndex};
 use sdl2::rect::Rect;
+use std::collections::HashMap;
 
 const MAX_OBJECTS: usize = 5;
 const MAX_LEVELS: i32 = 10;
 
-/// A quadtree for minimizing collision checks between actors
-pub struct Quadtree<'a, Type> {
+/// A quadtree for minimizing collision checks between actors.
+///
+/// The quadtree is in world coordinates so it can cover the whole map,
+/// including the actors that are outside of the viewport. Actors outside
+/// of the bounds of the quadtree are kept in the root node.
+///
+/// The quadtree is kept between frames and the actors are looked up by their
+/// index, so only the actors that move have to be updated every frame.
+pub struct Quadtree<Type> {
+    root: Node<Type>,
+    /// The rectangle that every actor was last inserted with
+    /// so that the node that holds the actor can be found again
+    rects: HashMap<ActorIndex, Rect>,
+}
+
+impl<Type: Copy> Quadtree<Type> {
+    pub fn new(bounds: Rect) -> Quadtree<Type> {
+        Quadtree {
+            root: Node::new(bounds, 0),
+            rects: HashMap::new(),
+        }
+    }
+
+    /// Returns the bounds of the quadtree in world coordinates
+    pub fn bounds(&self) -> Rect {
+        self.root.bounds
+    }
+}
+
+impl<Type: Copy + PartialEq> SpatialIndex<Type> for Quadtree<Type> {
+    fn insert(&mut self, actor: ActorData<Type>) {
+        self.remove(actor.index);
+        self.rects.insert(actor.index, actor.rect);
+        self.root.insert(actor);
+    }
+
+    fn remove(&mut self, index: ActorIndex) -> Option<ActorData<Type>> {
+        let rect = self.rects.remove(&index)?;
+        self.root.remove(index, &rect)
+    }
+
+    /// The actor is only moved to a different node
+    /// if it doesn't fit in its node anymore
+    fn update_position(&mut self, index: ActorIndex, rect: Rect) -> bool {
+        let Some(old_rect) = self.rects.get(&index).cloned() else {
+            return false;
+        };
+        if old_rect == rect {
+            return true;
+        }
+
+        self.rects.insert(index, rect);
+        self.root.update(index, &old_rect, rect)
+    }
+
+    fn get(&self, index: ActorIndex) -> Option<&ActorData<Type>> {
+        let rect = self.rects.get(&index)?;
+        self.root.get(index, rect)
+    }
+
+    fn get_mut(&mut self, index: ActorIndex) -> Option<&mut ActorData<Type>> {
+        let rect = self.rects.get(&index)?;
+        self.root.get_mut(index, rect)
+    }
+
+    fn contains(&self, index: ActorIndex) -> bool {
+        self.rects.contains_key(&index)
+    }
+
+    fn retain(&mut self, f: &mut dyn FnMut(ActorIndex) -> bool) {
+        let removed: Vec<_> = self.rects.keys().cloned().filter(|&i| !f(i)).collect();
+        for index in removed {
+            self.remove(index);
+        }
+    }
+
+    fn retrieve(&self, rect: &Rect) -> Vec<&ActorData<Type>> {
+        let mut retrieved_values = Vec::new();
+        self.root.retrieve(rect, &mut retrieved_values);
+        retrieved_values
+    }
+
+    fn query_rect(&self, rect: &Rect, filter: &QueryFilter<Type>) -> Vec<&ActorData<Type>> {
+        let mut found = Vec::new();
+        self.root
+            .visit(&|bounds| bounds.has_intersection(*rect), &mut |data| {
+                if filter.matches(data) && data.rect.has_intersection(*rect) {
+                    found.push(data);
+                }
+            });
+        found
+    }
+
+    fn query_point(&self, point: (i32, i32), filter: &QueryFilter<Type>) -> Vec<&ActorData<Type>> {
+        let mut found = Vec::new();
+        self.root
+            .visit(&|bounds| bounds.contains_point(point), &mut |data| {
+                if filter.matches(data) && data.rect.contains_point(point) {
+                    found.push(data);
+                }
+            });
+        found
+    }
+
+    fn query_radius(
+        &self,
+        center: (f64, f64),
+        radius: f64,
+        filter: &QueryFilter<Type>,
+    ) -> Vec<&ActorData<Type>> {
+        let within = |rect: &Rect| spatial::rect_distance(rect, center) <= radius;
+        let mut found = Vec::new();
+        self.root.visit(&within, &mut |data| {
+            if filter.matches(data) && within(&data.rect) {
+                found.push(data);
+            }
+        });
+        found
+    }
+
+    fn nearest(
+        &self,
+        point: (f64, f64),
+        k: usize,
+        filter: &QueryFilter<Type>,
+    ) -> Vec<&ActorData<Type>> {
+        let mut found = Vec::with_capacity(k + 1);
+        if k > 0 {
+            self.root.nearest(point, k, filter, &mut found);
+        }
+        found.into_iter().map(|(_, data)| data).collect()
+    }
+
+    fn len(&self) -> usize {
+        self.rects.len()
+    }
+}
+
+/// A node of the quadtree that holds the actors that don't fit in one of its subnodes
+struct Node<Type> {
     /// The level of the current tree, (0 is root)
     level: i32,
     /// The actors that the current tree holds
     objects: Vec<ActorData<Type>>,
     /// An array of 4 subtrees to split into when parent is full
-    nodes: [Option<Box<Quadtree<'a, Type>>>; 4],
+    nodes: [Option<Box<Node<Type>>>; 4],
     /// The bounds of the current tree
     bounds: Rect,
-    /// The viewport so that all points are adjusted to the view
-    viewport: &'a Viewport,
 }
 
-impl<'a, Type> Quadtree<'a, Type> {
-    pub fn new(rect: Rect, viewport: &'a Viewport) -> Quadtree<'a, Type> {
-        Quadtree {
-            level: 0,
+impl<Type: Copy> Node<Type> {
+    fn new(bounds: Rect, level: i32) -> Node<Type> {
+        Node {
+            level,
             objects: Vec::with_capacity(MAX_OBJECTS),
-            bounds: rect,
             nodes: [None, None, None, None],
-            viewport,
+            bounds,
         }
     }
 
+    /// Returns the x and y coordinates that split the bounds into quadrants
+    fn midpoints(&self) -> (i32, i32) {
+        (
+            self.bounds.x() + self.bounds.width() as i32 / 2,
+            self.bounds.y() + self.bounds.height() as i32 / 2,
+        )
+    }
+
     /// Splits the node into four subnodes
     fn split(&mut self) {
-        let width = (f64::from(self.bounds.width()) / 2.0) as i32;
-        let height = (f64::from(self.bounds.height()) / 2.0) as i32;
+        let (vert_mid, horiz_mid) = self.midpoints();
+        let left_width = (vert_mid - self.bounds.left()) as u32;
+        let right_width = (self.bounds.right() - vert_mid) as u32;
+        let top_height = (horiz_mid - self.bounds.top()) as u32;
+        let bottom_height = (self.bounds.bottom() - horiz_mid) as u32;
         let (x, y) = (self.bounds.x(), self.bounds.y());
 
-        if width as u32 > 0u32 && height as u32 > 0u32 {
-            self.nodes[0] = Some(Box::new(Quadtree {
-                level: self.level + 1,
-                objects: Vec::with_capacity(MAX_OBJECTS),
-                bounds: Rect::new(x + width, y, width as u32, height as u32),
-                nodes: [None, None, None, None],
-                viewport: self.viewport,
-            }));
-            self.nodes[1] = Some(Box::new(Quadtree {
-                level: self.level + 1,
-                objects: Vec::with_capacity(MAX_OBJECTS),
-                bounds: Rect::new(x, y, width as u32, height as u32),
-                nodes: [None, None, None, None],
-                viewport: self.viewport,
-            }));
-            self.nodes[2] = Some(Box::new(Quadtree {
-                level: self.level + 1,
-                objects: Vec::with_capacity(MAX_OBJECTS),
-                bounds: Rect::new(x, y + height, width as u32, height as u32),
-                nodes: [None, None, None, None],
-                viewport: self.viewport,
-            }));
-            self.nodes[3] = Some(Box::new(Quadtree {
-                level: self.level + 1,
-                objects: Vec::with_capacity(MAX_OBJECTS),
-                bounds: Rect::new(x + width, y + height, width as u32, height as u32),
-                nodes: [None, None, None, None],
-                viewport: self.viewport,
-            }));
-        }
-    }
-
-    /// Determine which node index the object belongs to
-    fn index(&self, rect: &Rect) -> Option<i32> {
-        self.viewport.constrain_to_viewport(rect).and_then(|rect| {
-            let vert_mid = f64::from(self.bounds.x()) + f64::from(self.bounds.width()) / 2.;
-            let horiz_mid = f64::from(self.bounds.y()) + f64::from(self.bounds.height()) / 2.;
-
-            let top_quad = f64::from(rect.y()) < horiz_mid
-                && f64::from(rect.y()) + f64::from(rect.height()) < horiz_mid;
-            let bot_quad = f64::from(rect.y()) > horiz_mid;
-
-            if f64::from(rect.x()) < vert_mid
-                && f64::from(rect.x()) + f64::from(rect.width()) < vert_mid
-            {
-                if top_quad {
-                    return Some(1);
-                } else if bot_quad {
-                    return Some(2);
-                }
-            } else if f64::from(rect.x()) > vert_mid {
-                if top_quad {
-                    return Some(0);
-                } else if bot_quad {
-                    return Some(3);
-                }
+        if left_width > 0 && top_height > 0 {
+            let level = self.level + 1;
+            let quadrants = [
+                Rect::new(vert_mid, y, right_width, top_height),
+                Rect::new(x, y, left_width, top_height),
+                Rect::new(x, horiz_mid, left_width, bottom_height),
+                Rect::new(vert_mid, horiz_mid, right_width, bottom_height),
+            ];
+            for (node, bounds) in self.nodes.iter_mut().zip(quadrants) {
+                *node = Some(Box::new(Node::new(bounds, level)));
             }
+        }
+    }
 
-            None
-        })
+    /// Determine which node index the object belongs to,
+    /// or None if it doesn't fit completely inside a quadrant
+    fn index(&self, rect: &Rect) -> Option<usize> {
+        let (vert_mid, horiz_mid) = self.midpoints();
+
+        let top_quad = rect.top() >= self.bounds.top() && rect.bottom() <= horiz_mid;
+        let bot_quad = rect.top() >= horiz_mid && rect.bottom() <= self.bounds.bottom();
+
+        if rect.left() >= self.bounds.left() && rect.right() <= vert_mid {
+            if top_quad {
+                return Some(1);
+            } else if bot_quad {
+                return Some(2);
+            }
+        } else if rect.left() >= vert_mid && rect.right() <= self.bounds.right() {
+            if top_quad {
+                return Some(0);
+            } else if bot_quad {
+                return Some(3);
+            }
+        }
+
+        None
     }
 
-    /// Inserts an actor into the quadtree
-    pub fn insert(&mut self, actor: ActorData<Type>) {
-        if self.nodes[0].is_some() {
-            if let Some(index) = self.index(&actor.rect) {
-                if let Some(ref mut node) = self.nodes[index as usize] {
-                    node.insert(actor);
-                }
-                return;
+    /// Returns the subnode that a rectangle belongs to if the node is split
+    fn subnode(&self, rect: &Rect) -> Option<usize> {
+        self.nodes[0].as_ref()?;
+        self.index(rect)
+    }
+
+    /// Inserts an actor into the node
+    fn insert(&mut self, actor: ActorData<Type>) {
+        if let Some(index) = self.subnode(&actor.rect) {
+            if let Some(ref mut node) = self.nodes[index] {
+                node.insert(actor);
             }
+            return;
         }
 
         if self.objects.len() == MAX_OBJECTS && self.level < MAX_LEVELS {
             if self.nodes[0].is_none() {
                 self.split();
             }
+            if self.nodes[0].is_none() {
+                self.objects.push(actor);
+                return;
+            }
 
             let mut leftover_parent = Vec::with_capacity(MAX_OBJECTS);
+            self.objects.push(actor);
             while let Some(object) = self.objects.pop() {
-                if let Some(index) = self.index(&object.rect) {
-                    if let Some(ref mut node) = self.nodes[index as usize] {
-                        node.insert(object);
+                match self.index(&object.rect) {
+                    Some(index) => {
+                        if let Some(ref mut node) = self.nodes[index] {
+                            node.insert(object);
+                        }
                     }
-                } else {
-                    leftover_parent.push(object);
-                }
-            }
-
-            // Handle the overflowing actor also
-            if let Some(index) = self.index(&actor.rect) {
-                if let Some(ref mut node) = self.nodes[index as usize] {
-                    node.insert(actor);
+                    None => leftover_parent.push(object),
                 }
-            } else {
-                leftover_parent.push(actor);
             }
 
             self.objects = leftover_parent;
@@ -140,46 +266,288 @@ impl<'a, Type> Quadtree<'a, Type> {
         }
     }
 
-    /// Return all objects that could collide
-    pub fn retrieve(&mut self, rect: &Rect) -> Vec<&ActorData<Type>> {
-        let mut retrieved_values = Vec::new();
+    /// Removes an actor that was inserted with a rectangle
+    fn remove(&mut self, actor: ActorIndex, rect: &Rect) -> Option<ActorData<Type>> {
+        if let Some(index) = self.subnode(rect) {
+            return self.nodes[index].as_mut()?.remove(actor, rect);
+        }
+
+        let position = self.objects.iter().position(|data| data.index == actor)?;
+        Some(self.objects.swap_remove(position))
+    }
+
+    /// Moves an actor that was inserted with the old rectangle to the new
+    /// rectangle, reinserting it into the deepest node that holds both rectangles
+    fn update(&mut self, actor: ActorIndex, old_rect: &Rect, rect: Rect) -> bool {
+        match (self.subnode(old_rect), self.subnode(&rect)) {
+            (Some(old_index), Some(index)) if old_index == index => match self.nodes[index] {
+                Some(ref mut node) => node.update(actor, old_rect, rect),
+                None => false,
+            },
+            (old_index, index) => {
+                let data = match old_index {
+                    Some(old_index) => self.nodes[old_index]
+                        .as_mut()
+                        .and_then(|node| node.remove(actor, old_rect)),
+                    None => {
+                        let position = self.objects.iter().position(|data| data.index == actor);
+                        match (position, index) {
+                            // The actor stays in this node so it is moved in place
+                            (Some(position), None) => {
+                                spatial::move_data(&mut self.objects[position], rect);
+                                return true;
+                            }
+                            (position, _) => position.map(|p| self.objects.swap_remove(p)),
+                        }
+                    }
+                };
+
+                match data {
+                    Some(mut data) => {
+                        spatial::move_data(&mut data, rect);
+                        self.insert(data);
+                        true
+                    }
+                    None => false,
+                }
+            }
+        }
+    }
+
+    fn get(&self, actor: ActorIndex, rect: &Rect) -> Option<&ActorData<Type>> {
+        match self.subnode(rect) {
+            Some(index) => self.nodes[index].as_ref()?.get(actor, rect),
+            None => self.objects.iter().find(|data| data.index == actor),
+        }
+    }
+
+    fn get_mut(&mut self, actor: ActorIndex, rect: &Rect) -> Option<&mut ActorData<Type>> {
+        match self.subnode(rect) {
+            Some(index) => self.nodes[index].as_mut()?.get_mut(actor, rect),
+            None => self.objects.iter_mut().find(|data| data.index == actor),
+        }
+    }
+
+    /// Calls a function with the actors in the nodes that overlap a region
+    fn visit<'a, R, F>(&'a self, overlaps: &R, f: &mut F)
+    where
+        R: Fn(&Rect) -> bool,
+        F: FnMut(&'a ActorData<Type>),
+    {
+        for object in &self.objects {
+            f(object);
+        }
+        for node in self.nodes.iter().flatten() {
+            if overlaps(&node.bounds) {
+                node.visit(overlaps, f);
+            }
+        }
+    }
+
+    /// Adds all objects that could collide with a rectangle
+    fn retrieve<'a>(&'a self, rect: &Rect, retrieved_values: &mut Vec<&'a ActorData<Type>>) {
         if let Some(index) = self.index(rect) {
-            if let Some(ref mut node) = self.nodes[index as usize] {
-                retrieved_values.extend(node.retrieve(rect));
+            if let Some(ref node) = self.nodes[index] {
+                node.retrieve(rect, retrieved_values);
             }
         } else {
             // if current object is not in a quadrant add all of the children
             // since it could potentially collide with other objects in a quadrant
-            for node in &mut self.nodes[..] {
-                if let Some(ref mut node) = *node {
-                    retrieved_values.extend(node.retrieve(rect).into_iter());
-                }
+            for node in self.nodes.iter().flatten() {
+                node.retrieve(rect, retrieved_values);
             }
         }
 
-        for object in &self.objects {
-            if object.rect != *rect {
-                retrieved_values.push(object);
+        retrieved_values.extend(self.objects.iter());
+    }
+}
+
+impl<Type: Copy + PartialEq> Node<Type> {
+    /// Keeps the k closest actors to a point in found sorted by distance,
+    /// skipping the subnodes that are further away than the furthest actor found
+    fn nearest<'a>(
+        &'a self,
+        point: (f64, f64),
+        k: usize,
+        filter: &QueryFilter<Type>,
+        found: &mut Vec<(f64, &'a ActorData<Type>)>,
+    ) {
+        for object in self.objects.iter().filter(|data| filter.matches(data)) {
+            let distance = spatial::rect_distance(&object.rect, point);
+            let position = found.partition_point(|&(d, _)| d <= distance);
+            if position < k {
+                found.insert(position, (distance, object));
+                found.truncate(k);
             }
         }
 
-        retrieved_values
+        let mut nodes: Vec<_> = self
+            .nodes
+            .iter()
+            .flatten()
+            .map(|node| (spatial::rect_distance(&node.bounds, point), node))
+            .collect();
+        nodes.sort_by(|(d1, _), (d2, _)| d1.total_cmp(d2));
+        for (distance, node) in nodes {
+            if found.len() == k && found.last().is_some_and(|&(d, _)| distance > d) {
+                break;
+            }
+            node.nearest(point, k, filter, found);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor_manager::ActorIndex;
+    use crate::collision::CollisionLayers;
+    use crate::test_utils::actor_data;
+
+    fn retrieved_ids(quadtree: &Quadtree<()>, rect: &Rect) -> Vec<usize> {
+        let mut ids: Vec<_> = quadtree
+            .retrieve(rect)
+            .into_iter()
+            .map(|data| data.index.id)
+            .collect();
+        ids.sort();
+        ids
     }
 
-    /// Returns the total number of elements in the quadtree
-    pub fn len(&self) -> usize {
-        let mut l = self.objects.len();
+    #[test]
+    fn test_index() {
+        let node = Node::<()>::new(Rect::new(-100, 0, 200, 200), 0);
+        assert_eq!(node.index(&Rect::new(10, 10, 20, 20)), Some(0));
+        assert_eq!(node.index(&Rect::new(-100, 0, 100, 100)), Some(1));
+        assert_eq!(node.index(&Rect::new(-50, 150, 20, 20)), Some(2));
+        assert_eq!(node.index(&Rect::new(0, 100, 100, 100)), Some(3));
 
-        for i in 0..4 {
-            if let Some(ref node) = self.nodes[i] {
-                l += node.len();
-            }
+        // Rectangles that cross the middle or leave the bounds fit no quadrant
+        assert_eq!(node.index(&Rect::new(-10, 10, 20, 20)), None);
+        assert_eq!(node.index(&Rect::new(10, 90, 20, 20)), None);
+        assert_eq!(node.index(&Rect::new(90, 10, 20, 20)), None);
+    }
+
+    #[test]
+    fn test_world_space() {
+        // A map much larger than the window with actors all over it
+        let mut quadtree = Quadtree::new(Rect::new(0, 0, 4000, 800));
+        for id in 0..40 {
+            let rect = Rect::new(id as i32 * 100, 700, 40, 40);
+            quadtree.insert(actor_data(id, rect));
         }
+        quadtree.insert(actor_data(40, Rect::new(-500, 0, 40, 40)));
+        assert_eq!(quadtree.len(), 41);
 
-        l
+        // Far away actors are found without any viewport
+        let ids = retrieved_ids(&quadtree, &Rect::new(3510, 710, 20, 20));
+        assert!(ids.contains(&35));
+        assert!(!ids.contains(&0));
+
+        // Actors outside of the bounds stay in the root node
+        let ids = retrieved_ids(&quadtree, &Rect::new(-490, 10, 20, 20));
+        assert!(ids.contains(&40));
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+    #[test]
+    fn test_remove_and_update() {
+        let mut quadtree = Quadtree::new(Rect::new(0, 0, 800, 800));
+        for id in 0..20 {
+            let rect = Rect::new((id % 5) as i32 * 150, (id / 5) as i32 * 150, 40, 40);
+            quadtree.insert(actor_data(id, rect));
+        }
+        let player = ActorIndex {
+            id: 7,
+            generation: 0,
+        };
+
+        // Inserting an actor again replaces it
+        quadtree.insert(actor_data(7, Rect::new(300, 150, 40, 40)));
+        assert_eq!(quadtree.len(), 20);
+
+        // Moving inside the same node and into a different node
+        assert!(quadtree.update_position(player, Rect::new(310, 150, 40, 40)));
+        assert!(quadtree.update_position(player, Rect::new(700, 700, 40, 40)));
+        assert_eq!(
+            quadtree.get(player).unwrap().rect,
+            Rect::new(700, 700, 40, 40)
+        );
+        assert!(retrieved_ids(&quadtree, &Rect::new(710, 710, 10, 10)).contains(&7));
+        assert!(!retrieved_ids(&quadtree, &Rect::new(300, 150, 10, 10)).contains(&7));
+
+        // Updating the data replaces it
+        quadtree.update(ActorData {
+            state: 3,
+            ..actor_data(7, Rect::new(10, 700, 40, 40))
+        });
+        assert_eq!(quadtree.get(player).unwrap().state, 3);
+        assert!(retrieved_ids(&quadtree, &Rect::new(20, 710, 10, 10)).contains(&7));
+
+        assert_eq!(quadtree.remove(player).unwrap().index, player);
+        assert!(quadtree.remove(player).is_none());
+        assert!(!quadtree.update_position(player, Rect::new(0, 0, 10, 10)));
+        assert_eq!(quadtree.len(), 19);
+
+        quadtree.retain(&mut |index| index.id < 10);
+        assert_eq!(quadtree.len(), 9);
+        assert!(!quadtree.contains(ActorIndex {
+            id: 15,
+            generation: 0
+        }));
+        assert!(retrieved_ids(&quadtree, &Rect::new(0, 0, 800, 800)).len() == 9);
+    }
+
+    #[test]
+    fn test_identical_rects() {
+        let mut quadtree = Quadtree::new(Rect::new(0, 0, 800, 800));
+        let rect = Rect::new(100, 100, 40, 40);
+        quadtree.insert(actor_data(0, rect));
+        quadtree.insert(actor_data(1, rect));
+
+        assert_eq!(retrieved_ids(&quadtree, &rect), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_queries() {
+        let mut quadtree = Quadtree::new(Rect::new(0, 0, 1000, 1000));
+        for id in 0..100 {
+            let rect = Rect::new((id % 10) as i32 * 100, (id / 10) as i32 * 100, 50, 50);
+            let layer = if id % 2 == 0 { 0b01 } else { 0b10 };
+            quadtree.insert(ActorData {
+                layers: CollisionLayers::new(layer, 0),
+                ..actor_data(id, rect)
+            });
+        }
+        let all = QueryFilter::new();
+        let ids = |found: Vec<&ActorData<()>>| {
+            let mut ids: Vec<_> = found.into_iter().map(|data| data.index.id).collect();
+            ids.sort();
+            ids
+        };
+
+        let found = quadtree.query_rect(&Rect::new(120, 120, 100, 100), &all);
+        assert_eq!(ids(found), vec![11, 12, 21, 22]);
+        let found = quadtree.query_rect(&Rect::new(120, 120, 100, 100), &all.mask(0b10));
+        assert_eq!(ids(found), vec![11, 21]);
+
+        assert_eq!(ids(quadtree.query_point((525, 325), &all)), vec![35]);
+        assert!(quadtree.query_point((575, 325), &all).is_empty());
+
+        // The actors at (400, 400) and the three around the corner at (450, 450)
+        let found = quadtree.query_radius((475., 475.), 40., &all);
+        assert_eq!(ids(found), vec![44, 45, 54, 55]);
+
+        let nearest = quadtree.nearest((1000., 1000.), 3, &all);
+        let nearest: Vec<_> = nearest.into_iter().map(|data| data.index.id).collect();
+        assert_eq!(nearest[0], 99);
+        assert_eq!(
+            ids(quadtree.nearest((1000., 1000.), 3, &all)),
+            vec![89, 98, 99]
+        );
+        assert_eq!(
+            ids(quadtree.nearest((1000., 1000.), 2, &all.mask(0b01))),
+            vec![88, 98]
+        );
+        assert!(quadtree.nearest((0., 0.), 0, &all).is_empty());
     }
 }
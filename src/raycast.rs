@@ -1,4 +1,4 @@
-use crate::collision::CollisionSide;
+use crate::collision::{self, BoundingBox, Collision, CollisionSide};
 use crate::vector::Vector2D;
 use crate::viewport::Viewport;
 use sdl2::pixels::Color;
@@ -114,20 +114,279 @@ impl Polygon for Rect {
     }
 }
 
-/// Shortens a ray segment agains a polygon
-pub fn shorten_ray<P: Polygon>(ray: &mut Segment, poly: &P) -> Option<CollisionSide> {
+/// The closest point where a ray crosses a side of a polygon
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    /// The point where the ray crosses the side
+    pub point: (f64, f64),
+    /// The unit normal of the side that was hit pointing out of the polygon
+    pub normal: Vector2D,
+    /// The distance from the start of the ray to the point
+    pub distance: f64,
+    /// The side of the polygon that was hit
+    pub side: Option<CollisionSide>,
+}
+
+/// Casts a ray against a polygon with sides wound like a rectangle's sides
+/// and returns the closest side that the ray crosses.
+///
+/// A ray that starts inside a polygon hits the side that it leaves through,
+/// so the normal of the hit points in the same direction as the ray.
+pub fn cast_ray<P: Polygon + ?Sized>(ray: &Segment, poly: &P) -> Option<RayHit> {
+    let mut closest: Option<RayHit> = None;
     for (id, side) in poly.sides().iter().enumerate() {
-        if let Some((int_x, int_y)) = ray.intersects(side) {
-            // Shorten the ray by the distance between the intersection point
-            // and the endpoint of the ray
-            let (end_x, end_y) = (ray.point.0 + ray.vector.x, ray.point.1 + ray.vector.y);
-            let distance = ((end_x - int_x).powi(2) + (end_y - int_y).powi(2)).sqrt();
-            let coll_side = poly.collision_from_side(id);
-            *ray = ray.shorten(distance);
-            return coll_side;
+        let normal = Vector2D {
+            x: -side.vector.y,
+            y: side.vector.x,
+        }
+        .normalize();
+
+        if let Some(point) = ray.intersects(side) {
+            let distance =
+                ((point.0 - ray.point.0).powi(2) + (point.1 - ray.point.1).powi(2)).sqrt();
+            if closest.is_none_or(|hit| distance < hit.distance) {
+                closest = Some(RayHit {
+                    point,
+                    normal,
+                    distance,
+                    side: poly.collision_from_side(id),
+                });
+            }
+        }
+    }
+
+    closest
+}
+
+/// Casts a ray against a polygon like `cast_ray` and returns the closest side
+/// that the ray enters through. Rays that start inside a polygon pass through it.
+pub fn cast_ray_entering<P: Polygon + ?Sized>(ray: &Segment, poly: &P) -> Option<RayHit> {
+    cast_ray(ray, poly).filter(|hit| ray.vector.x * hit.normal.x + ray.vector.y * hit.normal.y < 0.)
+}
+
+/// Shortens a ray segment against a polygon so that it ends at the closest
+/// side that it crosses and returns that side
+pub fn shorten_ray<P: Polygon>(ray: &mut Segment, poly: &P) -> Option<CollisionSide> {
+    let hit = cast_ray(ray, poly)?;
+    // Shorten the ray by the distance between the intersection point
+    // and the endpoint of the ray
+    *ray = ray.shorten(ray.length() - hit.distance);
+    hit.side
+}
+
+/// The first contact of a ray or a moving shape with a polygon
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SweepHit {
+    /// The fraction of the movement from 0 to 1 before the contact
+    pub time: f64,
+    /// The unit normal of the side that was hit pointing out of the polygon
+    pub normal: Vector2D,
+    /// The side of the polygon that was hit
+    pub side: Option<CollisionSide>,
+}
+
+/// Casts a ray against a polygon with sides wound like a rectangle's sides
+/// and returns the first side that the ray enters through.
+///
+/// Sides that the ray moves away from are ignored and a corner is only hit
+/// if the ray moves into both sides at the corner, so that shapes can slide
+/// along a surface made of multiple polygons but can't slip through a corner.
+pub fn sweep_ray<P: Polygon>(ray: &Segment, poly: &P) -> Option<SweepHit> {
+    let length = ray.length();
+    if length == 0. {
+        return None;
+    }
+
+    let sides = poly.sides();
+    // The ray moves into a side if it moves against the side's outward normal
+    let moves_into =
+        |side: &Segment| ray.vector.x * -side.vector.y + ray.vector.y * side.vector.x < 0.;
+
+    let mut first_hit: Option<SweepHit> = None;
+    for (id, side) in sides.iter().enumerate() {
+        if !moves_into(side) {
+            continue;
+        }
+
+        let Some((int_x, int_y)) = ray.intersects(side) else {
+            continue;
+        };
+
+        // Hits at the ends of the side are corner hits that only count if the
+        // ray also moves into the side on the other side of the corner,
+        // otherwise it grazes the corner or slides along the other side
+        let side_length = side.length();
+        let along = ((int_x - side.point.0) * side.vector.x
+            + (int_y - side.point.1) * side.vector.y)
+            / (side_length * side_length);
+        let adjacent = if along <= 0. {
+            Some((id + sides.len() - 1) % sides.len())
+        } else if along >= 1. {
+            Some((id + 1) % sides.len())
+        } else {
+            None
+        };
+        if adjacent.is_some_and(|adjacent| !moves_into(&sides[adjacent])) {
+            continue;
+        }
+
+        let normal = Vector2D {
+            x: -side.vector.y,
+            y: side.vector.x,
+        }
+        .normalize();
+
+        let distance = ((int_x - ray.point.0).powi(2) + (int_y - ray.point.1).powi(2)).sqrt();
+        let time = distance / length;
+        if first_hit.is_none_or(|hit| time < hit.time) {
+            first_hit = Some(SweepHit {
+                time,
+                normal,
+                side: poly.collision_from_side(id),
+            });
         }
     }
-    None
+
+    first_hit
+}
+
+/// Sweeps a rectangle along a movement vector and returns the first contact
+/// with another rectangle, or None if it doesn't move into the other rectangle.
+///
+/// The rectangle is swept by casting a ray from its top left corner against
+/// the other rectangle grown by the size of the swept rectangle.
+pub fn sweep_rect(rect: &Rect, movement: &Vector2D, other: &Rect) -> Option<SweepHit> {
+    let expanded = Rect::new(
+        other.x() - rect.width() as i32,
+        other.y() - rect.height() as i32,
+        other.width() + rect.width(),
+        other.height() + rect.height(),
+    );
+    let ray = Segment {
+        point: (f64::from(rect.x()), f64::from(rect.y())),
+        vector: *movement,
+    };
+
+    sweep_ray(&ray, &expanded)
+}
+
+/// Sweeps a shape along a movement vector and returns the first contact
+/// with another shape, or None if it doesn't move into the other shape.
+/// Shapes that already overlap are hit at time 0 with the normal that separates them
+/// only if the shape moves further into the other shape, so that shapes resting
+/// a pixel into the ground can still move away from it or slide along it.
+///
+/// The shape is swept by casting a ray from the origin against the
+/// Minkowski difference of the shapes, which contains every offset
+/// that the shape can move by to overlap the other shape.
+pub fn cast_shape(
+    shape: &BoundingBox,
+    movement: &Vector2D,
+    other: &BoundingBox,
+) -> Option<SweepHit> {
+    if let Some(contact) = shape.contact(other)
+        && contact.depth > 0.
+    {
+        if movement.x * contact.normal.x + movement.y * contact.normal.y >= 0. {
+            return None;
+        }
+        return Some(SweepHit {
+            time: 0.,
+            normal: contact.normal,
+            side: Some(collision::normal_side(&contact.normal)),
+        });
+    }
+
+    let ray = Segment {
+        point: (0., 0.),
+        vector: *movement,
+    };
+    sweep_ray(&ray, &MinkowskiDifference::new(shape, other))
+}
+
+/// The convex polygon of the offsets between the points
+/// of two convex polygons, wound like a rectangle's sides
+struct MinkowskiDifference {
+    points: Vec<(f64, f64)>,
+}
+
+impl MinkowskiDifference {
+    fn new<P: Polygon, Q: Polygon>(moving: &P, other: &Q) -> MinkowskiDifference {
+        let moving_points: Vec<_> = moving.sides().iter().map(|side| side.point).collect();
+        let mut points = Vec::new();
+        for side in other.sides() {
+            let (x, y) = side.point;
+            points.extend(moving_points.iter().map(|&(mx, my)| (x - mx, y - my)));
+        }
+
+        MinkowskiDifference {
+            points: convex_hull(points),
+        }
+    }
+}
+
+impl Polygon for MinkowskiDifference {
+    fn sides(&self) -> Vec<Segment> {
+        let len = self.points.len();
+        (0..len)
+            .map(|i| {
+                let (point, next) = (self.points[i], self.points[(i + 1) % len]);
+                Segment {
+                    point,
+                    vector: Vector2D {
+                        x: next.0 - point.0,
+                        y: next.1 - point.1,
+                    },
+                }
+            })
+            .collect()
+    }
+
+    fn collision_from_side(&self, id: usize) -> Option<CollisionSide> {
+        let side = self.sides().into_iter().nth(id)?;
+        let normal = Vector2D {
+            x: -side.vector.y,
+            y: side.vector.x,
+        };
+        Some(collision::normal_side(&normal))
+    }
+}
+
+/// Returns the convex hull of a set of points wound like a rectangle's sides
+/// without any points in the middle of a side
+fn convex_hull(mut points: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    points.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let mut hull: Vec<(f64, f64)> = Vec::with_capacity(points.len() + 1);
+    for pass in 0..2 {
+        let start = hull.len();
+        for i in 0..points.len() {
+            let point = if pass == 0 {
+                points[i]
+            } else {
+                points[points.len() - 1 - i]
+            };
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        // the last point is the first point of the other half
+        hull.pop();
+    }
+
+    // the hull goes the opposite way of a rectangle's sides
+    hull.reverse();
+    hull
 }
 
 /// Returns the point where two lines intersect
@@ -161,6 +420,8 @@ fn get_intersection(
 mod tests {
     use super::*;
     use crate::collision::CollisionSide;
+    use crate::shape::Circle;
+    use crate::sprite::SpriteRectangle;
     use crate::vector::Vector2D;
     use sdl2::rect::Rect;
 
@@ -267,6 +528,153 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_sweep_rect() {
+        let rect = Rect::new(0, 0, 10, 10);
+        let block = Rect::new(0, 50, 10, 10);
+
+        let hit = sweep_rect(&rect, &Vector2D { x: 0., y: 100. }, &block).unwrap();
+        assert_float(hit.time, 0.4);
+        assert_eq!(hit.normal, Vector2D { x: 0., y: -1. });
+        assert_eq!(hit.side, Some(CollisionSide::Top));
+
+        // Moving away from the block
+        assert_eq!(
+            sweep_rect(&rect, &Vector2D { x: 0., y: -100. }, &block),
+            None
+        );
+
+        // Touching the block and moving into it
+        let rect = Rect::new(0, 40, 10, 10);
+        let hit = sweep_rect(&rect, &Vector2D { x: 0., y: 5. }, &block).unwrap();
+        assert_float(hit.time, 0.);
+    }
+
+    #[test]
+    fn test_sweep_rect_slide() {
+        // Sliding along the top of a block doesn't hit the next block
+        let rect = Rect::new(0, 40, 10, 10);
+        let next_block = Rect::new(10, 50, 10, 10);
+        assert_eq!(
+            sweep_rect(&rect, &Vector2D { x: 20., y: 0. }, &next_block),
+            None
+        );
+
+        let wall = Rect::new(20, 0, 10, 60);
+        let hit = sweep_rect(&rect, &Vector2D { x: 20., y: 0. }, &wall).unwrap();
+        assert_float(hit.time, 0.5);
+        assert_eq!(hit.side, Some(CollisionSide::Left));
+    }
+
+    #[test]
+    fn test_sweep_rect_corner() {
+        // Moving diagonally straight into the corner of a block hits it
+        let rect = Rect::new(0, 0, 10, 10);
+        let block = Rect::new(20, 20, 10, 10);
+        let hit = sweep_rect(&rect, &Vector2D { x: 40., y: 40. }, &block).unwrap();
+        assert_float(hit.time, 0.25);
+
+        // Passing by the corner only touches it
+        let block = Rect::new(20, 0, 10, 10);
+        assert_eq!(
+            sweep_rect(&rect, &Vector2D { x: 40., y: -40. }, &block),
+            None
+        );
+    }
+
+    #[test]
+    fn test_shorten_ray_nearest() {
+        // The left side comes first in side order but the right side is closer
+        let rect = Rect::new(2, 0, 2, 4);
+        let mut segment = Segment {
+            point: (6., 1.),
+            vector: Vector2D { x: -6., y: 0. },
+        };
+
+        let side = shorten_ray(&mut segment, &rect);
+        assert_eq!(side, Some(CollisionSide::Right));
+        assert_float(segment.length(), 2.);
+
+        // Rays that start inside the polygon end where they leave it
+        let mut segment = Segment {
+            point: (3., 1.),
+            vector: Vector2D { x: -6., y: 0. },
+        };
+        assert_eq!(shorten_ray(&mut segment, &rect), Some(CollisionSide::Left));
+        assert_float(segment.length(), 1.);
+    }
+
+    #[test]
+    fn test_cast_ray() {
+        let rect = Rect::new(10, 10, 10, 10);
+        let ray = Segment {
+            point: (0., 0.),
+            vector: Vector2D { x: 30., y: 30. },
+        };
+
+        let hit = cast_ray(&ray, &rect).unwrap();
+        assert_float(hit.point.0, 10.);
+        assert_float(hit.point.1, 10.);
+        assert_float(hit.distance, 200f64.sqrt());
+        assert!(hit.normal.x < 0. || hit.normal.y < 0.);
+    }
+
+    #[test]
+    fn test_convex_hull() {
+        let points = vec![(0., 0.), (1., 1.), (2., 0.), (2., 2.), (0., 2.), (1., 0.)];
+        let hull = convex_hull(points);
+        assert_eq!(hull.len(), 4);
+        // wound like the sides of a rectangle
+        let rect = Rect::new(0, 0, 2, 2);
+        let sides = MinkowskiDifference { points: hull }.sides();
+        for side in rect.sides() {
+            assert!(sides.contains(&side));
+        }
+    }
+
+    #[test]
+    fn test_cast_shape() {
+        let shape = BoundingBox::Rectangle(SpriteRectangle::new(0, 0, 10, 10));
+        let block = BoundingBox::Rectangle(SpriteRectangle::new(0, 50, 10, 10));
+
+        let hit = cast_shape(&shape, &Vector2D { x: 0., y: 100. }, &block).unwrap();
+        assert_float(hit.time, 0.4);
+        assert_eq!(hit.normal, Vector2D { x: 0., y: -1. });
+        assert_eq!(hit.side, Some(CollisionSide::Top));
+        assert_eq!(
+            cast_shape(&shape, &Vector2D { x: 0., y: -100. }, &block),
+            None
+        );
+
+        // Circles hit the side of a rectangle at the edge of the circle
+        let circle = BoundingBox::Circle(Circle::new((0., 55.), 5.));
+        let wall = BoundingBox::Rectangle(SpriteRectangle::new(20, 0, 10, 100));
+        let hit = cast_shape(&circle, &Vector2D { x: 30., y: 0. }, &wall).unwrap();
+        assert_float(hit.time, 0.5);
+        assert_eq!(hit.side, Some(CollisionSide::Left));
+
+        // Sliding along a surface doesn't hit it, even if the shapes overlap
+        let resting = BoundingBox::Rectangle(SpriteRectangle::new(0, 40, 10, 10));
+        assert_eq!(
+            cast_shape(&resting, &Vector2D { x: 30., y: 0. }, &block),
+            None
+        );
+        let overlapping = BoundingBox::Rectangle(SpriteRectangle::new(0, 45, 10, 10));
+        assert_eq!(
+            cast_shape(&overlapping, &Vector2D { x: 30., y: 0. }, &block),
+            None
+        );
+        assert_eq!(
+            cast_shape(&overlapping, &Vector2D { x: 0., y: -30. }, &block),
+            None
+        );
+
+        // Overlapping shapes that move further in are hit right away
+        let hit = cast_shape(&overlapping, &Vector2D { x: 10., y: 30. }, &block).unwrap();
+        assert_eq!(hit.time, 0.);
+        assert_eq!(hit.normal, Vector2D { x: 0., y: -1. });
+    }
+
     #[test]
     fn test_shorten_ray_top() {
         let rect = Rect::new(2, 3, 2, 2);
@@ -0,0 +1,261 @@
+use crate::collision::{self, CollisionSide};
+use crate::raycast::{Polygon, Segment};
+use crate::vector::{PositionChange, Vector2D};
+use sdl2::rect::Rect;
+use std::f64::consts::PI;
+
+/// The most points that a convex polygon can have
+pub const MAX_POLYGON_POINTS: usize = 8;
+
+/// The number of sides used when a circle is raycasted like a polygon
+const CIRCLE_SIDES: usize = 16;
+
+/// A circle shaped bounding box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Circle {
+    pub center: (f64, f64),
+    pub radius: f64,
+}
+
+impl Circle {
+    pub fn new(center: (f64, f64), radius: f64) -> Circle {
+        Circle { center, radius }
+    }
+
+    /// Creates the largest circle that fits inside a rectangle
+    pub fn from_rect(rect: &Rect) -> Circle {
+        Circle {
+            center: collision::center_point(rect),
+            radius: 0.5 * f64::from(rect.width().min(rect.height())),
+        }
+    }
+
+    /// Moves the circle by the position change
+    pub fn apply_change(&mut self, change: &PositionChange) {
+        self.center.0 += f64::from(change.x);
+        self.center.1 += f64::from(change.y);
+    }
+
+    /// Returns the smallest rectangle that contains the circle
+    pub fn bounding_rect(&self) -> Rect {
+        let diameter = (2. * self.radius).ceil() as u32;
+        Rect::new(
+            (self.center.0 - self.radius).floor() as i32,
+            (self.center.1 - self.radius).floor() as i32,
+            diameter,
+            diameter,
+        )
+    }
+
+    /// Returns the minimum and maximum of the circle projected onto an axis
+    pub fn project(&self, axis: &Vector2D) -> (f64, f64) {
+        let center = self.center.0 * axis.x + self.center.1 * axis.y;
+        (center - self.radius, center + self.radius)
+    }
+}
+
+impl Polygon for Circle {
+    fn sides(&self) -> Vec<Segment> {
+        let point = |i: usize| {
+            let angle = 2. * PI * i as f64 / CIRCLE_SIDES as f64;
+            (
+                self.center.0 + self.radius * angle.sin(),
+                self.center.1 + self.radius * angle.cos(),
+            )
+        };
+
+        (0..CIRCLE_SIDES)
+            .map(|i| side_between(point(i), point(i + 1)))
+            .collect()
+    }
+
+    fn collision_from_side(&self, id: usize) -> Option<CollisionSide> {
+        self.sides().get(id).map(side_facing)
+    }
+}
+
+/// A convex polygon shaped bounding box with up to MAX_POLYGON_POINTS points
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConvexPolygon {
+    points: [(f64, f64); MAX_POLYGON_POINTS],
+    len: usize,
+}
+
+impl ConvexPolygon {
+    /// Creates a convex polygon from its points in clockwise or
+    /// counterclockwise order
+    pub fn new(points: &[(f64, f64)]) -> ConvexPolygon {
+        assert!(
+            points.len() >= 3 && points.len() <= MAX_POLYGON_POINTS,
+            "A convex polygon needs 3 to {} points!",
+            MAX_POLYGON_POINTS
+        );
+
+        let mut polygon = ConvexPolygon {
+            points: [(0., 0.); MAX_POLYGON_POINTS],
+            len: points.len(),
+        };
+        polygon.points[..points.len()].copy_from_slice(points);
+
+        // Wind the points like the sides of a rectangle so that
+        // the normals of the sides point out of the polygon
+        let area: f64 = (0..points.len())
+            .map(|i| {
+                let (p1, p2) = (points[i], points[(i + 1) % points.len()]);
+                p1.0 * p2.1 - p2.0 * p1.1
+            })
+            .sum();
+        if area > 0. {
+            polygon.points[..points.len()].reverse();
+        }
+
+        polygon
+    }
+
+    /// Creates a polygon with the corners of a rectangle
+    pub fn from_rect(rect: &Rect) -> ConvexPolygon {
+        let (x, y) = (f64::from(rect.x()), f64::from(rect.y()));
+        let (w, h) = (f64::from(rect.width()), f64::from(rect.height()));
+        ConvexPolygon::new(&[(x, y), (x, y + h), (x + w, y + h), (x + w, y)])
+    }
+
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points[..self.len]
+    }
+
+    /// Returns the average of the points of the polygon
+    pub fn center(&self) -> (f64, f64) {
+        let (x, y) = self
+            .points()
+            .iter()
+            .fold((0., 0.), |(x, y), point| (x + point.0, y + point.1));
+        (x / self.len as f64, y / self.len as f64)
+    }
+
+    /// Moves the polygon by the position change
+    pub fn apply_change(&mut self, change: &PositionChange) {
+        for point in &mut self.points[..self.len] {
+            point.0 += f64::from(change.x);
+            point.1 += f64::from(change.y);
+        }
+    }
+
+    /// Returns the smallest rectangle that contains the polygon
+    pub fn bounding_rect(&self) -> Rect {
+        let (mut min_x, mut min_y) = (f64::MAX, f64::MAX);
+        let (mut max_x, mut max_y) = (f64::MIN, f64::MIN);
+        for &(x, y) in self.points() {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+
+        Rect::new(
+            min_x.floor() as i32,
+            min_y.floor() as i32,
+            (max_x.ceil() - min_x.floor()) as u32,
+            (max_y.ceil() - min_y.floor()) as u32,
+        )
+    }
+
+    /// Returns the unit normals of the sides pointing out of the polygon
+    pub fn normals(&self) -> Vec<Vector2D> {
+        self.sides().iter().map(outward_normal).collect()
+    }
+
+    /// Returns the minimum and maximum of the polygon projected onto an axis
+    pub fn project(&self, axis: &Vector2D) -> (f64, f64) {
+        self.points()
+            .iter()
+            .map(|point| point.0 * axis.x + point.1 * axis.y)
+            .fold((f64::MAX, f64::MIN), |(min, max), projection| {
+                (min.min(projection), max.max(projection))
+            })
+    }
+}
+
+impl Polygon for ConvexPolygon {
+    fn sides(&self) -> Vec<Segment> {
+        let points = self.points();
+        (0..points.len())
+            .map(|i| side_between(points[i], points[(i + 1) % points.len()]))
+            .collect()
+    }
+
+    fn collision_from_side(&self, id: usize) -> Option<CollisionSide> {
+        self.sides().get(id).map(side_facing)
+    }
+}
+
+/// Returns the unit normal of a side of a polygon wound like a rectangle
+pub fn outward_normal(side: &Segment) -> Vector2D {
+    Vector2D {
+        x: -side.vector.y,
+        y: side.vector.x,
+    }
+    .normalize()
+}
+
+/// Returns the side of a shape that a polygon side faces
+fn side_facing(side: &Segment) -> CollisionSide {
+    collision::normal_side(&outward_normal(side))
+}
+
+fn side_between(p1: (f64, f64), p2: (f64, f64)) -> Segment {
+    Segment {
+        point: p1,
+        vector: Vector2D {
+            x: p2.0 - p1.0,
+            y: p2.1 - p1.1,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polygon_winding() {
+        let clockwise = ConvexPolygon::new(&[(0., 0.), (10., 0.), (10., 10.), (0., 10.)]);
+        let rect = ConvexPolygon::from_rect(&Rect::new(0, 0, 10, 10));
+
+        for normal in clockwise.normals() {
+            assert!(rect.normals().contains(&normal));
+        }
+        assert_eq!(
+            rect.normals(),
+            vec![
+                Vector2D { x: -1., y: 0. },
+                Vector2D { x: 0., y: 1. },
+                Vector2D { x: 1., y: 0. },
+                Vector2D { x: 0., y: -1. },
+            ]
+        );
+        assert_eq!(rect.collision_from_side(3), Some(CollisionSide::Top));
+    }
+
+    #[test]
+    fn test_bounding_rect() {
+        let triangle = ConvexPolygon::new(&[(0., 10.), (5., 0.5), (10., 10.)]);
+        assert_eq!(triangle.bounding_rect(), Rect::new(0, 0, 10, 10));
+
+        let circle = Circle::new((10., 10.), 5.);
+        assert_eq!(circle.bounding_rect(), Rect::new(5, 5, 10, 10));
+        assert_eq!(circle.project(&Vector2D { x: 0., y: 1. }), (5., 15.));
+    }
+
+    #[test]
+    fn test_circle_sides() {
+        let circle = Circle::new((0., 0.), 10.);
+        let sides = circle.sides();
+
+        assert_eq!(sides.len(), CIRCLE_SIDES);
+        assert_eq!(circle.collision_from_side(0), Some(CollisionSide::Bottom));
+        assert_eq!(
+            circle.collision_from_side(CIRCLE_SIDES / 2),
+            Some(CollisionSide::Top)
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use crate::ActorData;
+use crate::actor_manager::ActorIndex;
+use crate::collision::ALL_LAYERS;
+use crate::quadtree::Quadtree;
+use crate::spatial_hash::SpatialHash;
+use crate::vector::PositionChange;
+use sdl2::rect::Rect;
+
+/// A broadphase index of actors in world coordinates that finds the
+/// actors near a rectangle or a point without checking every actor.
+///
+/// The index is kept between frames and the actors are looked up by
+/// their index, so only the actors that move have to be updated.
+pub trait SpatialIndex<Type: Copy + PartialEq> {
+    /// Inserts an actor into the index, replacing the actor
+    /// with the same index if it is already in the index
+    fn insert(&mut self, actor: ActorData<Type>);
+
+    /// Removes an actor from the index and returns its data,
+    /// or None if the actor isn't in the index
+    fn remove(&mut self, index: ActorIndex) -> Option<ActorData<Type>>;
+
+    /// Moves an actor in the index to a new rectangle and moves its bounding
+    /// box with it. Returns false if the actor isn't in the index.
+    fn update_position(&mut self, index: ActorIndex, rect: Rect) -> bool;
+
+    /// Returns the data of an actor in the index
+    fn get(&self, index: ActorIndex) -> Option<&ActorData<Type>>;
+
+    /// Returns the data of an actor in the index to change
+    /// anything about the actor except its rectangle
+    fn get_mut(&mut self, index: ActorIndex) -> Option<&mut ActorData<Type>>;
+
+    /// Removes every actor that the predicate returns false for,
+    /// like actors that were removed from the actor manager
+    fn retain(&mut self, f: &mut dyn FnMut(ActorIndex) -> bool);
+
+    /// Return all objects that could collide, including
+    /// the actor with the rectangle if it is in the index
+    fn retrieve(&self, rect: &Rect) -> Vec<&ActorData<Type>>;
+
+    /// Returns the actors that overlap a rectangle
+    fn query_rect(&self, rect: &Rect, filter: &QueryFilter<Type>) -> Vec<&ActorData<Type>>;
+
+    /// Returns the actors that contain a point, like the actors under the mouse
+    fn query_point(&self, point: (i32, i32), filter: &QueryFilter<Type>) -> Vec<&ActorData<Type>>;
+
+    /// Returns the actors with a rectangle that is at most a radius away from a point
+    fn query_radius(
+        &self,
+        center: (f64, f64),
+        radius: f64,
+        filter: &QueryFilter<Type>,
+    ) -> Vec<&ActorData<Type>>;
+
+    /// Returns the k actors closest to a point sorted from closest to furthest.
+    /// The distance to an actor is the distance to the closest point of its rectangle.
+    fn nearest(
+        &self,
+        point: (f64, f64),
+        k: usize,
+        filter: &QueryFilter<Type>,
+    ) -> Vec<&ActorData<Type>>;
+
+    /// Returns the total number of actors in the index
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if an actor is in the index
+    fn contains(&self, index: ActorIndex) -> bool {
+        self.get(index).is_some()
+    }
+
+    /// Replaces the data of an actor with newer data, moving the actor
+    /// if its rectangle changed, or inserts the actor if it isn't in the index
+    fn update(&mut self, actor: ActorData<Type>) {
+        if self.update_position(actor.index, actor.rect)
+            && let Some(data) = self.get_mut(actor.index)
+        {
+            *data = actor;
+        } else {
+            self.insert(actor);
+        }
+    }
+}
+
+/// The kinds of spatial indexes a game can pick as its broadphase
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Broadphase {
+    /// A quadtree that splits the world where actors are crowded
+    Quadtree,
+    /// A uniform grid with square cells of a size in pixels, which
+    /// is faster when the actors are about the same size as a cell
+    SpatialHash(i32),
+}
+
+impl Broadphase {
+    /// Creates an empty spatial index covering the bounds of the world
+    pub fn create<Type>(&self, bounds: Rect) -> Box<dyn SpatialIndex<Type>>
+    where
+        Type: Copy + PartialEq + 'static,
+    {
+        match *self {
+            Broadphase::Quadtree => Box::new(Quadtree::new(bounds)),
+            Broadphase::SpatialHash(cell_size) => Box::new(SpatialHash::new(cell_size)),
+        }
+    }
+}
+
+/// Limits the actors returned by a spatial query to
+/// an actor type and to the collision layers in a mask
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueryFilter<Type> {
+    actor_type: Option<Type>,
+    mask: u32,
+}
+
+impl<Type: Copy + PartialEq> QueryFilter<Type> {
+    /// Creates a filter that matches every actor
+    pub fn new() -> QueryFilter<Type> {
+        QueryFilter {
+            actor_type: None,
+            mask: ALL_LAYERS,
+        }
+    }
+
+    /// Only matches the actors of an actor type
+    pub fn actor_type(&self, actor_type: Type) -> QueryFilter<Type> {
+        QueryFilter {
+            actor_type: Some(actor_type),
+            ..*self
+        }
+    }
+
+    /// Only matches the actors on one of the collision layers in the mask
+    pub fn mask(&self, mask: u32) -> QueryFilter<Type> {
+        QueryFilter { mask, ..*self }
+    }
+
+    /// Returns true if the actor passes the filter
+    pub fn matches(&self, actor: &ActorData<Type>) -> bool {
+        self.actor_type
+            .is_none_or(|actor_type| actor_type == actor.actor_type)
+            && self.mask & actor.layers.layer != 0
+    }
+}
+
+impl<Type: Copy + PartialEq> Default for QueryFilter<Type> {
+    fn default() -> QueryFilter<Type> {
+        QueryFilter::new()
+    }
+}
+
+/// Returns the distance from a point to the closest point of a rectangle,
+/// which is 0 if the point is inside the rectangle
+pub fn rect_distance(rect: &Rect, point: (f64, f64)) -> f64 {
+    let x = point
+        .0
+        .clamp(f64::from(rect.left()), f64::from(rect.right()));
+    let y = point
+        .1
+        .clamp(f64::from(rect.top()), f64::from(rect.bottom()));
+    ((point.0 - x).powi(2) + (point.1 - y).powi(2)).sqrt()
+}
+
+/// Moves the rectangle of an actor and its bounding box with it
+pub(crate) fn move_data<Type>(data: &mut ActorData<Type>, rect: Rect) {
+    let change = PositionChange {
+        x: rect.x() - data.rect.x(),
+        y: rect.y() - data.rect.y(),
+        ..PositionChange::new()
+    };
+    if let Some(ref mut bbox) = data.bounding_box {
+        bbox.apply_change(&change);
+    }
+    data.rect = rect;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision::{CollisionLayers, TileShape};
+
+    #[test]
+    fn test_rect_distance() {
+        let rect = Rect::new(0, 0, 10, 10);
+        assert_eq!(rect_distance(&rect, (5., 5.)), 0.);
+        assert_eq!(rect_distance(&rect, (15., 5.)), 5.);
+        assert_eq!(rect_distance(&rect, (13., 14.)), 5.);
+    }
+
+    #[test]
+    fn test_query_filter() {
+        let data = ActorData {
+            index: ActorIndex {
+                id: 0,
+                generation: 0,
+            },
+            state: 0,
+            damage: 0,
+            collision_filter: 0b1111,
+            resolves_collisions: false,
+            rect: Rect::new(0, 0, 10, 10),
+            bounding_box: None,
+            shape: TileShape::Solid,
+            layers: CollisionLayers::new(0b10, 0),
+            trigger: false,
+            actor_type: 'E',
+        };
+
+        assert!(QueryFilter::new().matches(&data));
+        assert!(QueryFilter::new().actor_type('E').mask(0b11).matches(&data));
+        assert!(!QueryFilter::new().actor_type('P').matches(&data));
+        assert!(!QueryFilter::new().mask(0b01).matches(&data));
+    }
+}
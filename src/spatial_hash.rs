@@ -0,0 +1,365 @@
+use super::ActorData;
+use crate::actor_manager::ActorIndex;
+use crate::spatial::{self, QueryFilter, SpatialIndex};
+use sdl2::rect::Rect;
+use std::collections::{HashMap, HashSet};
+
+/// A uniform grid for minimizing collision checks between actors.
+///
+/// The world is split into square cells and every actor is kept in all
+/// of the cells that its rectangle covers. Unlike the quadtree the grid
+/// has no bounds, so the cells are only created where there are actors.
+/// It works best when most actors are about the size of a cell.
+pub struct SpatialHash<Type> {
+    cell_size: i32,
+    actors: HashMap<ActorIndex, ActorData<Type>>,
+    cells: HashMap<(i32, i32), Vec<ActorIndex>>,
+    /// The first and last cells that ever had an actor in them,
+    /// which limits how far the nearest actor search has to look
+    extent: Option<((i32, i32), (i32, i32))>,
+}
+
+impl<Type: Copy> SpatialHash<Type> {
+    pub fn new(cell_size: i32) -> SpatialHash<Type> {
+        assert!(cell_size > 0, "cell size must be positive");
+        SpatialHash {
+            cell_size,
+            actors: HashMap::new(),
+            cells: HashMap::new(),
+            extent: None,
+        }
+    }
+
+    /// Returns the width and height of a cell in pixels
+    pub fn cell_size(&self) -> i32 {
+        self.cell_size
+    }
+
+    /// Returns the first and last cells covered by a rectangle
+    fn cell_range(&self, rect: &Rect) -> ((i32, i32), (i32, i32)) {
+        let cell = |x: i32, y: i32| (x.div_euclid(self.cell_size), y.div_euclid(self.cell_size));
+        (
+            cell(rect.left(), rect.top()),
+            cell(rect.right() - 1, rect.bottom() - 1),
+        )
+    }
+
+    fn add_to_cells(&mut self, index: ActorIndex, rect: &Rect) {
+        let ((x1, y1), (x2, y2)) = self.cell_range(rect);
+        self.extent = Some(match self.extent {
+            Some(((ex1, ey1), (ex2, ey2))) => {
+                ((ex1.min(x1), ey1.min(y1)), (ex2.max(x2), ey2.max(y2)))
+            }
+            None => ((x1, y1), (x2, y2)),
+        });
+        for x in x1..=x2 {
+            for y in y1..=y2 {
+                self.cells.entry((x, y)).or_default().push(index);
+            }
+        }
+    }
+
+    fn remove_from_cells(&mut self, index: ActorIndex, rect: &Rect) {
+        let ((x1, y1), (x2, y2)) = self.cell_range(rect);
+        for x in x1..=x2 {
+            for y in y1..=y2 {
+                if let Some(cell) = self.cells.get_mut(&(x, y)) {
+                    cell.retain(|&i| i != index);
+                    if cell.is_empty() {
+                        self.cells.remove(&(x, y));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the actors in the cells covered by a rectangle
+    /// sorted by index without duplicates
+    fn candidates(&self, rect: &Rect) -> Vec<&ActorData<Type>> {
+        let ((x1, y1), (x2, y2)) = self.cell_range(rect);
+        let mut indexes = Vec::new();
+        for x in x1..=x2 {
+            for y in y1..=y2 {
+                if let Some(cell) = self.cells.get(&(x, y)) {
+                    indexes.extend(cell.iter().cloned());
+                }
+            }
+        }
+        indexes.sort();
+        indexes.dedup();
+
+        indexes
+            .into_iter()
+            .filter_map(|index| self.actors.get(&index))
+            .collect()
+    }
+}
+
+impl<Type: Copy + PartialEq> SpatialIndex<Type> for SpatialHash<Type> {
+    fn insert(&mut self, actor: ActorData<Type>) {
+        self.remove(actor.index);
+        self.add_to_cells(actor.index, &actor.rect);
+        self.actors.insert(actor.index, actor);
+    }
+
+    fn remove(&mut self, index: ActorIndex) -> Option<ActorData<Type>> {
+        let actor = self.actors.remove(&index)?;
+        self.remove_from_cells(index, &actor.rect);
+        Some(actor)
+    }
+
+    /// The actor is only moved to different cells
+    /// if it covers different cells than before
+    fn update_position(&mut self, index: ActorIndex, rect: Rect) -> bool {
+        let Some(old_rect) = self.actors.get(&index).map(|data| data.rect) else {
+            return false;
+        };
+        if self.cell_range(&old_rect) != self.cell_range(&rect) {
+            self.remove_from_cells(index, &old_rect);
+            self.add_to_cells(index, &rect);
+        }
+
+        if let Some(data) = self.actors.get_mut(&index) {
+            spatial::move_data(data, rect);
+        }
+        true
+    }
+
+    fn get(&self, index: ActorIndex) -> Option<&ActorData<Type>> {
+        self.actors.get(&index)
+    }
+
+    fn get_mut(&mut self, index: ActorIndex) -> Option<&mut ActorData<Type>> {
+        self.actors.get_mut(&index)
+    }
+
+    fn contains(&self, index: ActorIndex) -> bool {
+        self.actors.contains_key(&index)
+    }
+
+    fn retain(&mut self, f: &mut dyn FnMut(ActorIndex) -> bool) {
+        let removed: Vec<_> = self.actors.keys().cloned().filter(|&i| !f(i)).collect();
+        for index in removed {
+            self.remove(index);
+        }
+    }
+
+    fn retrieve(&self, rect: &Rect) -> Vec<&ActorData<Type>> {
+        self.candidates(rect)
+    }
+
+    fn query_rect(&self, rect: &Rect, filter: &QueryFilter<Type>) -> Vec<&ActorData<Type>> {
+        self.candidates(rect)
+            .into_iter()
+            .filter(|data| filter.matches(data) && data.rect.has_intersection(*rect))
+            .collect()
+    }
+
+    fn query_point(&self, point: (i32, i32), filter: &QueryFilter<Type>) -> Vec<&ActorData<Type>> {
+        self.candidates(&Rect::new(point.0, point.1, 1, 1))
+            .into_iter()
+            .filter(|data| filter.matches(data) && data.rect.contains_point(point))
+            .collect()
+    }
+
+    fn query_radius(
+        &self,
+        center: (f64, f64),
+        radius: f64,
+        filter: &QueryFilter<Type>,
+    ) -> Vec<&ActorData<Type>> {
+        let left = (center.0 - radius).floor() as i32;
+        let top = (center.1 - radius).floor() as i32;
+        let size = (radius * 2.).ceil() as u32 + 1;
+        self.candidates(&Rect::new(left, top, size, size))
+            .into_iter()
+            .filter(|data| {
+                filter.matches(data) && spatial::rect_distance(&data.rect, center) <= radius
+            })
+            .collect()
+    }
+
+    /// Searches the rings of cells around the point from the inside out
+    /// and stops once the next ring is further away than the furthest actor found
+    fn nearest(
+        &self,
+        point: (f64, f64),
+        k: usize,
+        filter: &QueryFilter<Type>,
+    ) -> Vec<&ActorData<Type>> {
+        let Some(((ex1, ey1), (ex2, ey2))) = self.extent else {
+            return Vec::new();
+        };
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let size = f64::from(self.cell_size);
+        let (cx, cy) = (
+            (point.0 / size).floor() as i32,
+            (point.1 / size).floor() as i32,
+        );
+        let last_ring = [cx - ex1, ex2 - cx, cy - ey1, ey2 - cy]
+            .into_iter()
+            .max()
+            .unwrap_or(0)
+            .max(0);
+
+        let mut seen = HashSet::new();
+        let mut found: Vec<(f64, &ActorData<Type>)> = Vec::with_capacity(k + 1);
+        for ring in 0..=last_ring {
+            for (x, y) in ring_cells((cx, cy), ring) {
+                let Some(cell) = self.cells.get(&(x, y)) else {
+                    continue;
+                };
+
+                for index in cell {
+                    if !seen.insert(*index) {
+                        continue;
+                    }
+                    let Some(data) = self.actors.get(index) else {
+                        continue;
+                    };
+                    if !filter.matches(data) {
+                        continue;
+                    }
+
+                    let distance = spatial::rect_distance(&data.rect, point);
+                    let position = found.partition_point(|&(d, _)| d <= distance);
+                    if position < k {
+                        found.insert(position, (distance, data));
+                        found.truncate(k);
+                    }
+                }
+            }
+
+            // every actor not seen yet is at least this far away
+            let unseen_distance = f64::from(ring) * size;
+            let done = found.len() == k && found.last().is_some_and(|&(d, _)| d <= unseen_distance);
+            if done || seen.len() == self.actors.len() {
+                break;
+            }
+        }
+
+        found.into_iter().map(|(_, data)| data).collect()
+    }
+
+    fn len(&self) -> usize {
+        self.actors.len()
+    }
+}
+
+/// Returns the cells on the border of the square of cells
+/// that are a number of cells away from the center cell
+fn ring_cells((cx, cy): (i32, i32), ring: i32) -> impl Iterator<Item = (i32, i32)> {
+    let sides = (cx - ring..=cx + ring).flat_map(move |x| {
+        let bottom = (ring > 0).then_some((x, cy + ring));
+        std::iter::once((x, cy - ring)).chain(bottom)
+    });
+    let ends = (cy - ring + 1..cy + ring).flat_map(move |y| [(cx - ring, y), (cx + ring, y)]);
+    sides.chain(ends)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision::CollisionLayers;
+    use crate::quadtree::Quadtree;
+    use crate::test_utils::actor_data;
+
+    fn ids(found: Vec<&ActorData<()>>) -> Vec<usize> {
+        let mut ids: Vec<_> = found.into_iter().map(|data| data.index.id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_cells() {
+        let mut hash = SpatialHash::new(50);
+        hash.insert(actor_data(0, Rect::new(40, 40, 20, 20)));
+        hash.insert(actor_data(1, Rect::new(-30, 0, 20, 20)));
+        assert_eq!(hash.cells.len(), 5);
+        assert_eq!(
+            hash.cells[&(-1, 0)],
+            vec![ActorIndex {
+                id: 1,
+                generation: 0
+            }]
+        );
+
+        // An actor covering four cells is only retrieved once
+        assert_eq!(ids(hash.retrieve(&Rect::new(0, 0, 100, 100))), vec![0]);
+        assert_eq!(ids(hash.retrieve(&Rect::new(-50, 0, 150, 50))), vec![0, 1]);
+
+        // Moving inside the same cells and into different cells
+        let index = ActorIndex {
+            id: 0,
+            generation: 0,
+        };
+        assert!(hash.update_position(index, Rect::new(45, 45, 20, 20)));
+        assert_eq!(hash.cells.len(), 5);
+        assert!(hash.update_position(index, Rect::new(210, 210, 20, 20)));
+        assert_eq!(hash.cells.len(), 2);
+        assert_eq!(hash.get(index).unwrap().rect, Rect::new(210, 210, 20, 20));
+        assert!(hash.retrieve(&Rect::new(40, 40, 20, 20)).is_empty());
+
+        assert_eq!(hash.remove(index).unwrap().index, index);
+        assert!(!hash.update_position(index, Rect::new(0, 0, 10, 10)));
+        hash.retain(&mut |_| false);
+        assert!(hash.is_empty());
+        assert!(hash.cells.is_empty());
+    }
+
+    #[test]
+    fn test_ring_cells() {
+        assert_eq!(ring_cells((2, 3), 0).collect::<Vec<_>>(), vec![(2, 3)]);
+        let mut cells: Vec<_> = ring_cells((0, 0), 2).collect();
+        cells.sort();
+        cells.dedup();
+        assert_eq!(cells.len(), 16);
+        assert!(cells.iter().all(|&(x, y)| x.abs() == 2 || y.abs() == 2));
+    }
+
+    #[test]
+    fn test_same_as_quadtree() {
+        let mut quadtree = Quadtree::new(Rect::new(0, 0, 1000, 1000));
+        let mut hash = SpatialHash::new(64);
+        for id in 0..100 {
+            let rect = Rect::new((id % 10) as i32 * 100, (id / 10) as i32 * 100, 50, 50);
+            let layer = if id % 2 == 0 { 0b01 } else { 0b10 };
+            let data = ActorData {
+                layers: CollisionLayers::new(layer, 0),
+                ..actor_data(id, rect)
+            };
+            quadtree.insert(data);
+            hash.insert(data);
+        }
+        let all = QueryFilter::new();
+
+        let rect = Rect::new(120, 120, 100, 100);
+        assert_eq!(ids(hash.query_rect(&rect, &all)), vec![11, 12, 21, 22]);
+        assert_eq!(
+            ids(hash.query_rect(&rect, &all.mask(0b10))),
+            ids(quadtree.query_rect(&rect, &all.mask(0b10)))
+        );
+        assert_eq!(ids(hash.query_point((525, 325), &all)), vec![35]);
+        assert!(hash.query_point((575, 325), &all).is_empty());
+        assert_eq!(
+            ids(hash.query_radius((475., 475.), 40., &all)),
+            vec![44, 45, 54, 55]
+        );
+
+        for &(point, k) in &[((1000., 1000.), 3), ((-500., 320.), 4), ((433., 612.), 6)] {
+            assert_eq!(
+                ids(hash.nearest(point, k, &all)),
+                ids(quadtree.nearest(point, k, &all))
+            );
+        }
+        assert_eq!(hash.nearest((1000., 1000.), 3, &all)[0].index.id, 99);
+        assert_eq!(
+            ids(hash.nearest((1000., 1000.), 2, &all.mask(0b01))),
+            vec![88, 98]
+        );
+        assert!(hash.nearest((0., 0.), 0, &all).is_empty());
+    }
+}
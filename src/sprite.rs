@@ -1,6 +1,7 @@
 use crate::cache;
 use crate::collision;
-use crate::collision::{BoundingBox, Collision, CollisionSide};
+use crate::collision::{BoundingBox, Collision, CollisionSide, Contact};
+use crate::raycast::Polygon;
 use crate::vector::PositionChange;
 use crate::viewport::Viewport;
 use sdl2::image::LoadTexture;
@@ -12,6 +13,7 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::error::Error;
 use std::hash::Hash;
+use std::mem;
 use std::path::Path;
 use std::rc::Rc;
 
@@ -96,10 +98,20 @@ impl Sprite {
         let sprite_cache = cache::sprite_cache();
 
         // if sprite is cached, return from cache
-        if let Ok(ref cache) = sprite_cache.cache.lock() {
+        if let Ok(ref mut cache) = sprite_cache.cache.lock() {
             if let Some(sprite) = cache.get(path).cloned() {
                 return Ok(sprite);
             }
+
+            // share the texture with the same file loaded from a different path
+            let same_file = cache
+                .iter()
+                .find(|(cached_path, _)| cache::same_file(cached_path, path))
+                .map(|(_, sprite)| sprite.clone());
+            if let Some(sprite) = same_file {
+                cache.insert(path.to_owned(), sprite.clone());
+                return Ok(sprite);
+            }
         }
 
         // otherwise load sprite from texture
@@ -117,6 +129,13 @@ impl Sprite {
         sprite.map_err(From::from)
     }
 
+    /// Replaces the texture shared by the sprite and all of its sub-sprites
+    pub(crate) fn replace_texture(&self, texture: Texture) {
+        let old = mem::replace(&mut *self.tex.borrow_mut(), texture);
+        // textures are not destroyed when dropped with unsafe_textures
+        unsafe { old.destroy() };
+    }
+
     /// Returns a sub-sprite from a rectangle region of the original sprite
     pub fn region(&self, rect: Rect) -> Option<Sprite> {
         let new_src = Rect::new(
@@ -186,6 +205,22 @@ impl AnimatedSprite {
         self.set_frame_delay(1.0 / fps);
     }
 
+    /// Sets the time the animated sprite has been alive so that
+    /// animations driven by the same clock show the same frame
+    pub fn set_time(&mut self, time: f64) {
+        self.current_time = time;
+    }
+
+    /// Returns the frame shown at the current time
+    pub fn current_frame(&self) -> &Sprite {
+        assert!(
+            !self.frames.is_empty(),
+            "There as to be at least one frame!"
+        );
+        let current_frame = (self.current_time / self.frame_delay) as usize % self.frames.len();
+        &self.frames[current_frame]
+    }
+
     /// Updates the animated sprite with the elapsed time
     pub fn add_time(&mut self, elapsed: f64) {
         self.current_time += elapsed;
@@ -199,14 +234,7 @@ impl AnimatedSprite {
 impl Renderable for AnimatedSprite {
     /// Renders the current frame of the animated sprite
     fn render(&self, canvas: &mut Canvas<Window>, dest: Rect) -> Result<(), Box<dyn Error>> {
-        assert!(
-            !self.frames.is_empty(),
-            "There as to be at least one frame!"
-        );
-        let current_frame = (self.current_time / self.frame_delay) as usize % self.frames.len();
-
-        let frame = &self.frames[current_frame];
-        frame.render(canvas, dest)
+        self.current_frame().render(canvas, dest)
     }
 }
 
@@ -395,6 +423,15 @@ where
         None
     }
 
+    /// Returns the contact between the bounding box of the animation at
+    /// the state and another bounding box if they touch
+    pub fn contact(&mut self, s: &State, other_bbox: &Option<BoundingBox>) -> Option<Contact> {
+        match (self.bbox(s), other_bbox) {
+            (Some(bounding_box), Some(bbox)) => bounding_box.contact(bbox),
+            _ => None,
+        }
+    }
+
     /// Adds time to the current animation
     pub fn add_time(&mut self, s: &State, elapsed: f64) {
         let _ = self.anim_mut(s).map(|ref mut anim| anim.add_time(elapsed));
@@ -418,6 +455,12 @@ where
                         let rect = Rect::new(rx, ry, rect.w, rect.h);
                         canvas.fill_rect(rect)?;
                     }
+                    ref polygon => {
+                        let color = ::sdl2::pixels::Color::RGB(230, 230, 230);
+                        for side in polygon.sides() {
+                            side.render(color, viewport, canvas)?;
+                        }
+                    }
                 }
             }
         }
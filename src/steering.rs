@@ -0,0 +1,301 @@
+use crate::collision::TileShape;
+use crate::level::GRID_SIZE;
+use crate::sprite::Direction;
+use crate::tilemap::Tilemap;
+use crate::vector::{PositionChange, Vector2D};
+use sdl2::rect::Rect;
+
+/// Moves an actor by accelerating its velocity towards the velocities that
+/// the steering behaviors want, so that actors don't have to write their
+/// own speed and acceleration code.
+///
+/// The behaviors return the velocity that they want and can be combined
+/// by adding or scaling them before passing the sum to `steer`:
+///
+/// ```
+/// use mold2d::{Steering, Vector2D};
+///
+/// let mut steering = Steering::new(4., 0.5);
+/// let position = Vector2D { x: 0., y: 0. };
+/// let player = Vector2D { x: 100., y: 0. };
+/// let shell = Vector2D { x: 0., y: 50. };
+///
+/// let desired = steering.seek(position, player) + steering.flee(position, shell) * 0.5;
+/// let change = steering.steer(desired);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Steering {
+    /// The current velocity in pixels per frame
+    pub velocity: Vector2D,
+    /// The fastest that the actor can move in pixels per frame
+    pub max_speed: f64,
+    /// The fraction of the difference to the desired velocity
+    /// that is added to the velocity every frame
+    pub acceleration: f64,
+    /// The fractions of pixels left over from the previous changes
+    remainder: Vector2D,
+}
+
+impl Steering {
+    /// Creates a steering for an actor that isn't moving
+    pub fn new(max_speed: f64, acceleration: f64) -> Steering {
+        Steering {
+            velocity: Vector2D { x: 0., y: 0. },
+            max_speed,
+            acceleration,
+            remainder: Vector2D { x: 0., y: 0. },
+        }
+    }
+
+    /// Returns the velocity that moves straight to a target at full speed
+    pub fn seek(&self, position: Vector2D, target: Vector2D) -> Vector2D {
+        direction(target - position) * self.max_speed
+    }
+
+    /// Returns the velocity that moves straight away from a threat at full speed
+    pub fn flee(&self, position: Vector2D, threat: Vector2D) -> Vector2D {
+        direction(position - threat) * self.max_speed
+    }
+
+    /// Returns the velocity that moves to a target and slows
+    /// down inside of a radius so that it stops on the target
+    pub fn arrive(&self, position: Vector2D, target: Vector2D, slowing_radius: f64) -> Vector2D {
+        let offset = target - position;
+        let distance = offset.length();
+        if distance < slowing_radius {
+            direction(offset) * (self.max_speed * distance / slowing_radius)
+        } else {
+            direction(offset) * self.max_speed
+        }
+    }
+
+    /// Returns the velocity that moves to where a moving target is going to be
+    /// by the time the actor gets there, like an enemy chasing the player
+    pub fn chase(
+        &self,
+        position: Vector2D,
+        target: Vector2D,
+        target_velocity: Vector2D,
+    ) -> Vector2D {
+        let frames = if self.max_speed > 0. {
+            (target - position).length() / self.max_speed
+        } else {
+            0.
+        };
+        self.seek(position, target + target_velocity * frames)
+    }
+
+    /// Accelerates the velocity towards a desired velocity and returns
+    /// the change in position for the frame. The velocity never goes
+    /// above the max speed and fractions of pixels are kept for the next frame.
+    pub fn steer(&mut self, desired: Vector2D) -> PositionChange {
+        self.velocity = self.velocity + (desired - self.velocity) * self.acceleration;
+        let speed = self.velocity.length();
+        if speed > self.max_speed {
+            self.velocity = self.velocity * (self.max_speed / speed);
+        }
+
+        let movement = self.velocity + self.remainder;
+        let change = PositionChange::from_vector(&movement);
+        self.remainder = movement - change.to_vector();
+        change
+    }
+}
+
+/// Walks back and forth between points, like a guard on patrol
+#[derive(Clone, Debug, PartialEq)]
+pub struct Patrol {
+    points: Vec<Vector2D>,
+    current: usize,
+    /// How close the actor has to be to a point before going to the next one
+    radius: f64,
+}
+
+impl Patrol {
+    /// Creates a patrol that starts at the first point and loops
+    /// back to the first point after the last one
+    pub fn new(points: Vec<Vector2D>, radius: f64) -> Patrol {
+        Patrol {
+            points,
+            current: 0,
+            radius,
+        }
+    }
+
+    /// Returns the point that the actor is walking to, or None if there are no points
+    pub fn target(&self) -> Option<Vector2D> {
+        self.points.get(self.current).copied()
+    }
+
+    /// Moves on to the next point if the actor is close to the current one
+    /// and returns the point to walk to
+    pub fn update(&mut self, position: Vector2D) -> Option<Vector2D> {
+        let target = self.target()?;
+        if (target - position).length() <= self.radius {
+            self.current = (self.current + 1) % self.points.len();
+        }
+        self.target()
+    }
+
+    /// Returns the velocity that walks the patrol, slowing down at each point
+    pub fn steer(&mut self, steering: &Steering, position: Vector2D) -> Vector2D {
+        match self.update(position) {
+            Some(target) => steering.arrive(position, target, self.radius * 2.),
+            None => Vector2D { x: 0., y: 0. },
+        }
+    }
+}
+
+/// Returns the direction that an actor walking on the tiles of a tilemap
+/// should face so that it turns around before walking off of a ledge
+/// or into a wall, like a Koopa that stays on its platform
+pub fn turn_at_ledge<Type>(
+    tilemap: &Tilemap<Type>,
+    rect: &Rect,
+    direction: Direction,
+) -> Direction {
+    let ahead = match direction {
+        Direction::Left => rect.x() - 1,
+        Direction::Right => rect.right(),
+    };
+
+    let wall = (rect.y()..rect.bottom())
+        .step_by(GRID_SIZE as usize)
+        .chain([rect.bottom() - 1])
+        .any(|y| {
+            cell(tilemap, ahead, y)
+                .and_then(|(col, row)| tilemap.get(col, row))
+                .is_some_and(|tile| tile.collision_filter != 0 && tile.shape == TileShape::Solid)
+        });
+    let floor =
+        cell(tilemap, ahead, rect.bottom()).is_some_and(|(col, row)| tilemap.is_solid(col, row));
+
+    match (wall || !floor, direction) {
+        (false, direction) => direction,
+        (true, Direction::Left) => Direction::Right,
+        (true, Direction::Right) => Direction::Left,
+    }
+}
+
+/// Returns the grid cell of the tilemap that contains a point
+fn cell<Type>(tilemap: &Tilemap<Type>, x: i32, y: i32) -> Option<(usize, usize)> {
+    let (col, row) = (x.div_euclid(GRID_SIZE), y.div_euclid(GRID_SIZE));
+    (col >= 0 && row >= 0 && (col as usize) < tilemap.columns() && (row as usize) < tilemap.rows())
+        .then_some((col as usize, row as usize))
+}
+
+/// Returns the unit vector in the direction of a vector, or a zero vector for zero vectors
+fn direction(v: Vector2D) -> Vector2D {
+    if v.length() > 0. { v.normalize() } else { v }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision::CollisionLayers;
+    use crate::tilemap::TileType;
+
+    fn v(x: f64, y: f64) -> Vector2D {
+        Vector2D { x, y }
+    }
+
+    fn close(a: Vector2D, b: Vector2D) -> bool {
+        (a - b).length() < 1e-6
+    }
+
+    #[test]
+    fn test_seek_flee() {
+        let steering = Steering::new(5., 1.);
+        assert!(close(steering.seek(v(0., 0.), v(30., 40.)), v(3., 4.)));
+        assert!(close(steering.flee(v(0., 0.), v(30., 40.)), v(-3., -4.)));
+        assert!(close(steering.seek(v(1., 1.), v(1., 1.)), v(0., 0.)));
+    }
+
+    #[test]
+    fn test_arrive() {
+        let steering = Steering::new(10., 1.);
+        assert!(close(
+            steering.arrive(v(0., 0.), v(100., 0.), 50.),
+            v(10., 0.)
+        ));
+        assert!(close(
+            steering.arrive(v(75., 0.), v(100., 0.), 50.),
+            v(5., 0.)
+        ));
+        assert!(close(
+            steering.arrive(v(100., 0.), v(100., 0.), 50.),
+            v(0., 0.)
+        ));
+    }
+
+    #[test]
+    fn test_chase() {
+        // The target moves up so the chaser aims above it
+        let steering = Steering::new(10., 1.);
+        let velocity = steering.chase(v(0., 0.), v(100., 0.), v(0., -10.));
+        assert!(velocity.x > 0. && velocity.y < 0.);
+        assert!((velocity.length() - 10.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_steer() {
+        let mut steering = Steering::new(4., 0.5);
+        assert_eq!(steering.steer(v(4., 0.)), PositionChange::new().right(2));
+        assert_eq!(steering.steer(v(4., 0.)), PositionChange::new().right(3));
+        assert!(close(steering.velocity, v(3., 0.)));
+
+        // The velocity is capped at the max speed
+        steering.acceleration = 1.;
+        steering.steer(v(0., 100.));
+        assert!(close(steering.velocity, v(0., 4.)));
+
+        // Fractions of pixels add up over frames
+        let mut slow = Steering::new(0.5, 1.);
+        let total = (0..4).fold(PositionChange::new(), |total, _| {
+            total.chain(&slow.steer(v(0.5, 0.)))
+        });
+        assert_eq!(total, PositionChange::new().right(2));
+    }
+
+    #[test]
+    fn test_patrol() {
+        let steering = Steering::new(2., 1.);
+        let mut patrol = Patrol::new(vec![v(0., 0.), v(100., 0.)], 5.);
+        assert_eq!(patrol.update(v(50., 0.)), Some(v(0., 0.)));
+        assert_eq!(patrol.update(v(3., 0.)), Some(v(100., 0.)));
+        assert!(close(patrol.steer(&steering, v(50., 0.)), v(2., 0.)));
+        assert_eq!(patrol.update(v(100., 0.)), Some(v(0., 0.)));
+
+        let mut empty = Patrol::new(vec![], 5.);
+        assert_eq!(empty.update(v(0., 0.)), None);
+    }
+
+    #[test]
+    fn test_turn_at_ledge() {
+        // A platform from column 1 to column 4 with a wall on column 4
+        let mut tilemap = Tilemap::new(6, 4);
+        let block = tilemap.add_tile_type(TileType {
+            sprite: None,
+            animation: None,
+            collision_filter: 0b1111,
+            shape: TileShape::Solid,
+            layers: CollisionLayers::default(),
+            actor_type: (),
+        });
+        for col in 1..5 {
+            tilemap.set(col, 3, Some(block));
+        }
+        tilemap.set(4, 2, Some(block));
+
+        let rect = |x| Rect::new(x, 2 * GRID_SIZE, 30, GRID_SIZE as u32);
+        let right = Direction::Right;
+        let left = Direction::Left;
+
+        // Keeps walking in the middle of the platform
+        assert_eq!(turn_at_ledge(&tilemap, &rect(80), right), right);
+        assert_eq!(turn_at_ledge(&tilemap, &rect(80), left), left);
+        // Turns at the ledge on the left and the wall on the right
+        assert_eq!(turn_at_ledge(&tilemap, &rect(40), left), right);
+        assert_eq!(turn_at_ledge(&tilemap, &rect(130), right), left);
+    }
+}
@@ -0,0 +1,321 @@
+use super::Actor;
+use crate::actor_manager::{ActorIndex, ActorManager, ActorPosition, ActorToken};
+use crate::collision::center_point;
+use crate::level::{GRID_SIZE, LevelGrid};
+use crate::viewport::Viewport;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use std::collections::{HashMap, HashSet};
+
+/// The default width and height of a chunk in grid cells
+pub const CHUNK_SIZE: usize = 16;
+
+/// Spawns and despawns the actors of a level in square chunks of grid cells
+/// as the viewport approaches them so only nearby actors are alive.
+///
+/// Actors that move into another chunk stay alive as long as the chunk they
+/// are in is near the viewport. Actors that were removed by the game
+/// (like collected coins) don't respawn when their chunk is spawned again.
+/// Persistent actors like the player are never despawned, and neither are
+/// the actors that the game adds itself since the streamer doesn't track them.
+pub struct LevelStreamer {
+    grid: LevelGrid,
+    /// Width and height of a chunk in grid cells
+    chunk_size: usize,
+    /// Distance in pixels around the viewport where chunks are kept alive
+    margin: i32,
+    /// The chunks that are currently spawned
+    loaded: HashSet<(usize, usize)>,
+    /// The actors that are alive with the grid cell they were spawned from
+    live: HashMap<(usize, usize), ActorIndex>,
+    /// The grid cells whose actors were removed by the game
+    /// or were spawned as persistent actors
+    consumed: HashSet<(usize, usize)>,
+    /// Returns true for the tokens of actors that are never despawned
+    persistent: Box<dyn Fn(ActorToken) -> bool>,
+}
+
+impl LevelStreamer {
+    pub fn new(grid: LevelGrid, chunk_size: usize) -> LevelStreamer {
+        assert!(chunk_size > 0);
+
+        LevelStreamer {
+            grid,
+            chunk_size,
+            margin: chunk_size as i32 * GRID_SIZE / 2,
+            loaded: HashSet::new(),
+            live: HashMap::new(),
+            consumed: HashSet::new(),
+            persistent: Box::new(|_| false),
+        }
+    }
+
+    /// Sets which tokens spawn persistent actors that stay alive after they
+    /// leave the chunks near the viewport, like the player falling off the map
+    pub fn set_persistent<F>(&mut self, persistent: F)
+    where
+        F: Fn(ActorToken) -> bool + 'static,
+    {
+        self.persistent = Box::new(persistent);
+    }
+
+    /// Sets the distance in pixels around the viewport where chunks are kept alive
+    pub fn set_margin(&mut self, margin: i32) {
+        self.margin = margin;
+    }
+
+    /// Returns the level grid that is being streamed
+    pub fn grid(&self) -> &LevelGrid {
+        &self.grid
+    }
+
+    /// Returns the chunks that are currently spawned
+    pub fn loaded_chunks(&self) -> &HashSet<(usize, usize)> {
+        &self.loaded
+    }
+
+    /// Returns the chunk that contains a point in the level
+    pub fn chunk_at(&self, point: (i32, i32)) -> Option<(usize, usize)> {
+        if point.0 < 0 || point.1 < 0 {
+            return None;
+        }
+
+        let chunk_pixels = self.chunk_size as i32 * GRID_SIZE;
+        Some((
+            (point.0 / chunk_pixels) as usize,
+            (point.1 / chunk_pixels) as usize,
+        ))
+    }
+
+    /// Returns the chunk in the bottom right corner of the level
+    fn last_chunk(&self) -> (usize, usize) {
+        (
+            self.grid.columns().saturating_sub(1) / self.chunk_size,
+            self.grid.rows().saturating_sub(1) / self.chunk_size,
+        )
+    }
+
+    /// Returns the chunk that contains a point, or the chunk at the edge
+    /// of the level closest to the point for points outside of the level
+    fn edge_chunk(&self, point: (i32, i32)) -> (usize, usize) {
+        let (last_col, last_row) = self.last_chunk();
+        let chunk_pixels = self.chunk_size as i32 * GRID_SIZE;
+        (
+            ((point.0.max(0) / chunk_pixels) as usize).min(last_col),
+            ((point.1.max(0) / chunk_pixels) as usize).min(last_row),
+        )
+    }
+
+    /// Returns the chunks that overlap the viewport and its margin
+    pub fn chunks_near(&self, viewport: &Viewport) -> HashSet<(usize, usize)> {
+        let (width, height) = viewport.window_dimensions;
+        let min = (
+            (viewport.x - self.margin).max(0),
+            (viewport.y - self.margin).max(0),
+        );
+        let max = (
+            viewport.x + width + self.margin,
+            viewport.y + height + self.margin,
+        );
+
+        let mut chunks = HashSet::new();
+        if let (Some(min), Some(max)) = (self.chunk_at(min), self.chunk_at(max)) {
+            let (last_col, last_row) = self.last_chunk();
+            for col in min.0..=max.0.min(last_col) {
+                for row in min.1..=max.1.min(last_row) {
+                    chunks.insert((col, row));
+                }
+            }
+        }
+
+        chunks
+    }
+
+    /// Called every frame to spawn the chunks near the viewport and despawn
+    /// the actors that are in chunks far away from the viewport
+    pub fn update<A, F>(
+        &mut self,
+        manager: &mut ActorManager<A>,
+        viewport: &Viewport,
+        actor_for_token: F,
+        canvas: &mut Canvas<Window>,
+    ) where
+        A: Actor + ?Sized,
+        F: Fn(ActorToken, ActorIndex, ActorPosition, &mut Canvas<Window>) -> Box<A>,
+    {
+        let near = self.chunks_near(viewport);
+        self.despawn(manager, &near);
+
+        // Spawn the chunks that just came near the viewport
+        let spawned: Vec<_> = near.difference(&self.loaded).copied().collect();
+        for (chunk_col, chunk_row) in spawned {
+            let cols = chunk_col * self.chunk_size..(chunk_col + 1) * self.chunk_size;
+            for col in cols {
+                let rows = chunk_row * self.chunk_size..(chunk_row + 1) * self.chunk_size;
+                for row in rows {
+                    let cell = (col, row);
+                    if self.live.contains_key(&cell) || self.consumed.contains(&cell) {
+                        continue;
+                    }
+
+                    if let Some(token) = self.grid.get(col, row) {
+                        let position =
+                            ActorPosition(col as i32 * GRID_SIZE, row as i32 * GRID_SIZE);
+                        let next_index = manager.next_index();
+                        let index = next_index.index();
+                        manager.add(next_index, actor_for_token(token, index, position, canvas));
+                        self.track(cell, token, index);
+                    }
+                }
+            }
+        }
+
+        self.loaded = near;
+    }
+
+    /// Remembers the actor spawned from a grid cell. Persistent actors are left
+    /// untracked so they are never despawned and their cell never spawns again.
+    fn track(&mut self, cell: (usize, usize), token: ActorToken, index: ActorIndex) {
+        if (self.persistent)(token) {
+            self.consumed.insert(cell);
+        } else {
+            self.live.insert(cell, index);
+        }
+    }
+
+    /// Despawns the tracked actors that are no longer near the viewport
+    /// and remembers the actors that were removed by the game.
+    /// Actors outside of the level count as being in the chunk at the edge.
+    fn despawn<A>(&mut self, manager: &mut ActorManager<A>, near: &HashSet<(usize, usize)>)
+    where
+        A: Actor + ?Sized,
+    {
+        let mut despawned = Vec::new();
+        for (&cell, &index) in &self.live {
+            match manager.get_mut(index) {
+                Some(actor) => {
+                    let (x, y) = center_point(&actor.data().rect);
+                    if !near.contains(&self.edge_chunk((x as i32, y as i32))) {
+                        manager.remove(index);
+                        despawned.push(cell);
+                    }
+                }
+                None => {
+                    self.consumed.insert(cell);
+                    despawned.push(cell);
+                }
+            }
+        }
+        for cell in despawned {
+            self.live.remove(&cell);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context;
+    use crate::test_utils::TestActor;
+    use sdl2::rect::Rect;
+
+    #[test]
+    fn test_chunks_near() {
+        let window = context::Window {
+            title: "test",
+            width: 400,
+            height: 200,
+        };
+        let line = "=".repeat(100);
+        let grid = LevelGrid::parse(&[line.as_str(); 10].join("\n"));
+        let mut viewport = Viewport::new(&window, grid.dimensions());
+        let mut streamer = LevelStreamer::new(grid, 4);
+        streamer.set_margin(0);
+
+        // 4 cells of 40 pixels in a chunk means 160 pixels per chunk
+        let near = streamer.chunks_near(&viewport);
+        let mut expected = HashSet::new();
+        for col in 0..3 {
+            for row in 0..2 {
+                expected.insert((col, row));
+            }
+        }
+        assert_eq!(near, expected);
+
+        viewport.x = 1000;
+        viewport.y = 200;
+        let near = streamer.chunks_near(&viewport);
+        let mut expected = HashSet::new();
+        for col in 6..9 {
+            expected.insert((col, 1));
+            expected.insert((col, 2));
+        }
+        assert_eq!(near, expected);
+    }
+
+    #[test]
+    fn test_chunk_at() {
+        let streamer = LevelStreamer::new(LevelGrid::new(), 2);
+
+        assert_eq!(streamer.chunk_at((0, 0)), Some((0, 0)));
+        assert_eq!(streamer.chunk_at((79, 80)), Some((0, 1)));
+        assert_eq!(streamer.chunk_at((-1, 0)), None);
+    }
+
+    #[test]
+    fn test_persistent_actor_leaves_map() {
+        let grid = LevelGrid::parse("P C");
+        let mut streamer = LevelStreamer::new(grid, 4);
+        streamer.set_persistent(|ActorToken(token)| token == 'P');
+
+        let mut manager: ActorManager<TestActor> = ActorManager::new();
+        let mut spawn = |manager: &mut ActorManager<TestActor>, col: usize, token| {
+            let next_index = manager.next_index();
+            let index = next_index.index();
+            let rect = Rect::new(col as i32 * GRID_SIZE, 0, 30, 30);
+            manager.add(next_index, Box::new(TestActor { index, rect }));
+            streamer.track((col, 0), ActorToken(token), index);
+            index
+        };
+        let player = spawn(&mut manager, 0, 'P');
+        let coin = spawn(&mut manager, 2, 'C');
+
+        // The player falls below the map and every chunk is far away
+        manager.get_mut(player).unwrap().rect = Rect::new(0, 10000, 30, 30);
+        streamer.despawn(&mut manager, &HashSet::new());
+
+        assert!(manager.get_mut(player).is_some());
+        assert!(manager.get_mut(coin).is_none());
+        // The player's cell doesn't spawn a second player
+        assert!(streamer.consumed.contains(&(0, 0)));
+    }
+
+    #[test]
+    fn test_actor_leaves_map_near_viewport() {
+        let grid = LevelGrid::parse(&["K".repeat(8), "K".repeat(8)].join("\n"));
+        let mut streamer = LevelStreamer::new(grid, 4);
+
+        let mut manager: ActorManager<TestActor> = ActorManager::new();
+        let mut koopas = Vec::new();
+        for col in [0, 7] {
+            let next_index = manager.next_index();
+            let index = next_index.index();
+            let rect = Rect::new(col * GRID_SIZE, 0, 30, 30);
+            manager.add(next_index, Box::new(TestActor { index, rect }));
+            streamer.track((col as usize, 0), ActorToken('K'), index);
+            koopas.push(index);
+        }
+
+        // A koopa bounces above the top left corner of the map and another
+        // walks past the right side while the edge chunks are near
+        manager.get_mut(koopas[0]).unwrap().rect = Rect::new(-100, -500, 30, 30);
+        manager.get_mut(koopas[1]).unwrap().rect = Rect::new(1000, 0, 30, 30);
+        streamer.despawn(&mut manager, &HashSet::from([(0, 0), (1, 0)]));
+        assert!(koopas.iter().all(|&koopa| manager.get_mut(koopa).is_some()));
+
+        // They are despawned once their edge chunks are far away
+        streamer.despawn(&mut manager, &HashSet::from([(1, 0)]));
+        assert!(manager.get_mut(koopas[0]).is_none());
+        assert!(manager.get_mut(koopas[1]).is_some());
+    }
+}
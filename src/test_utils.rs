@@ -0,0 +1,60 @@
+//! Actors and actor data shared by the tests of the engine
+
+use super::{Actor, ActorData};
+use crate::actor_manager::ActorIndex;
+use crate::collision::{Collision, CollisionLayers, CollisionSide, TileShape};
+use crate::context::Context;
+use crate::vector::PositionChange;
+use crate::viewport::Viewport;
+use sdl2::rect::Rect;
+use std::error::Error;
+
+/// Returns the data of a solid actor that can be collided into on every side
+pub fn actor_data(id: usize, rect: Rect) -> ActorData<()> {
+    ActorData {
+        index: ActorIndex { id, generation: 0 },
+        state: 0,
+        damage: 0,
+        collision_filter: 0b1111,
+        resolves_collisions: false,
+        rect,
+        bounding_box: None,
+        shape: TileShape::Solid,
+        layers: CollisionLayers::default(),
+        trigger: false,
+        actor_type: (),
+    }
+}
+
+/// An actor that stands still and resolves its collisions
+pub struct TestActor {
+    pub index: ActorIndex,
+    pub rect: Rect,
+}
+
+impl Actor for TestActor {
+    type Type = ();
+    type Message = ();
+
+    fn render(&mut self, _: &mut Context, _: &mut Viewport, _: f64) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn handle_message(&mut self, _: &()) {}
+
+    fn collides_with(&mut self, other: &ActorData<()>) -> Option<CollisionSide> {
+        self.rect.collides_with(&other.rect)
+    }
+
+    fn update(&mut self, _: &mut Context, _: f64) -> PositionChange {
+        PositionChange::new()
+    }
+
+    fn data(&mut self) -> ActorData<()> {
+        ActorData {
+            index: self.index,
+            resolves_collisions: true,
+            ..actor_data(self.index.id, self.rect)
+        }
+    }
+}
@@ -0,0 +1,476 @@
+use super::ActorData;
+use crate::actor_manager::{ActorIndex, ActorToken};
+use crate::autotile::{self, AutotileRules};
+use crate::collision::{BoundingBox, CollisionLayers, TileShape};
+use crate::level::{GRID_SIZE, LevelGrid};
+use crate::sprite::{AnimatedSprite, Renderable, Sprite, SpriteRectangle, Spritesheet};
+use crate::viewport::Viewport;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use std::collections::HashMap;
+use std::error::Error;
+use std::ops::Range;
+
+/// The width in pixels of the vertical strips that slope tiles are rendered in
+const SLOPE_STRIP_WIDTH: i32 = 4;
+
+/// The actor index given to the actor data of tiles since tiles are not actors
+pub const TILE_INDEX: ActorIndex = ActorIndex {
+    id: usize::MAX,
+    generation: 0,
+};
+
+/// A kind of tile in a tilemap
+#[derive(Clone)]
+pub struct TileType<Type> {
+    /// The sprite to render for the tile, or None for invisible tiles
+    pub sprite: Option<Sprite>,
+    /// The animation rendered instead of the sprite for animated tiles.
+    /// Every tile of the type shares the animation so they show the same frame.
+    pub animation: Option<AnimatedSprite>,
+    /// A byte that contains the sides that actors can collide into,
+    /// 0 for tiles that actors pass through
+    pub collision_filter: u8,
+    /// The shape that actors stand on, like one-way platforms or slopes
+    pub shape: TileShape,
+    /// The collision layers of the tiles. Tiles never check for collisions
+    /// themselves so only the layer is used.
+    pub layers: CollisionLayers,
+    /// The actor type that actors see when they collide with the tile
+    pub actor_type: Type,
+}
+
+/// A dense grid of static tiles that are rendered and collided with
+/// without creating an actor for every tile
+pub struct Tilemap<Type> {
+    columns: usize,
+    rows: usize,
+    /// The index into the tile types for every grid cell in row order
+    tiles: Vec<Option<usize>>,
+    tile_types: Vec<TileType<Type>>,
+    /// The terrain tile that every tile type was autotiled from
+    terrains: Vec<usize>,
+    /// The tile types created by autotiling for a terrain and spritesheet index
+    variants: HashMap<(usize, i32), usize>,
+    /// The tile type for every token in the level grid the tilemap was created from
+    tokens: HashMap<char, usize>,
+}
+
+impl<Type> Tilemap<Type> {
+    /// Creates an empty tilemap with the given number of columns and rows
+    pub fn new(columns: usize, rows: usize) -> Tilemap<Type> {
+        Tilemap {
+            columns,
+            rows,
+            tiles: vec![None; columns * rows],
+            tile_types: Vec::new(),
+            terrains: Vec::new(),
+            variants: HashMap::new(),
+            tokens: HashMap::new(),
+        }
+    }
+
+    /// Creates a tilemap from the tokens in a level grid given a function that
+    /// returns the tile type for a token or None if the token isn't a tile
+    pub fn from_grid<F>(
+        grid: &LevelGrid,
+        tile_for_token: F,
+        canvas: &mut Canvas<Window>,
+    ) -> Tilemap<Type>
+    where
+        F: Fn(ActorToken, &mut Canvas<Window>) -> Option<TileType<Type>>,
+    {
+        let mut tilemap = Tilemap::new(grid.columns(), grid.rows());
+        let mut not_tiles = Vec::new();
+
+        for (ActorToken(token), position) in grid.tokens() {
+            let tile = match tilemap.tokens.get(&token) {
+                Some(&tile) => tile,
+                None if not_tiles.contains(&token) => continue,
+                None => match tile_for_token(ActorToken(token), canvas) {
+                    Some(tile_type) => {
+                        let tile = tilemap.add_tile_type(tile_type);
+                        tilemap.tokens.insert(token, tile);
+                        tile
+                    }
+                    None => {
+                        not_tiles.push(token);
+                        continue;
+                    }
+                },
+            };
+
+            let (col, row) = (position.0 / GRID_SIZE, position.1 / GRID_SIZE);
+            tilemap.set(col as usize, row as usize, Some(tile));
+        }
+
+        tilemap
+    }
+
+    /// Adds a tile type and returns the tile to set in grid cells
+    pub fn add_tile_type(&mut self, tile_type: TileType<Type>) -> usize {
+        self.tile_types.push(tile_type);
+        self.terrains.push(self.tile_types.len() - 1);
+        self.tile_types.len() - 1
+    }
+
+    /// Returns the tile for a token if the token was turned into tiles
+    pub fn token_tile(&self, ActorToken(token): ActorToken) -> Option<usize> {
+        self.tokens.get(&token).cloned()
+    }
+
+    /// Returns the tile type at a grid cell
+    pub fn get(&self, col: usize, row: usize) -> Option<&TileType<Type>> {
+        self.tile(col, row).map(|tile| &self.tile_types[tile])
+    }
+
+    /// Returns the tile at a grid cell
+    pub fn tile(&self, col: usize, row: usize) -> Option<usize> {
+        if col < self.columns && row < self.rows {
+            self.tiles[row * self.columns + col]
+        } else {
+            None
+        }
+    }
+
+    /// Sets or clears the tile at a grid cell inside the tilemap
+    pub fn set(&mut self, col: usize, row: usize, tile: Option<usize>) {
+        if col < self.columns && row < self.rows {
+            self.tiles[row * self.columns + col] = tile;
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the terrain tile at a grid cell, which is the tile
+    /// that autotiled tiles were created from
+    pub fn terrain(&self, col: i32, row: i32) -> Option<usize> {
+        if col < 0 || row < 0 {
+            return None;
+        }
+        self.tile(col as usize, row as usize)
+            .map(|tile| self.terrains[tile])
+    }
+
+    /// Returns the spritesheet index chosen by the rules for every
+    /// grid cell of a terrain
+    fn autotile_indices(&self, terrain: usize, rules: &AutotileRules) -> Vec<(usize, i32)> {
+        (0..self.tiles.len())
+            .filter_map(|i| {
+                let (col, row) = ((i % self.columns) as i32, (i / self.columns) as i32);
+                if self.terrain(col, row) != Some(terrain) {
+                    return None;
+                }
+
+                let bits = autotile::neighbor_bits(rules.neighborhood(), col, row, |col, row| {
+                    self.terrain(col, row) == Some(terrain)
+                });
+                Some((i, rules.index(bits)))
+            })
+            .collect()
+    }
+
+    /// Returns true if actors can collide into the tile at a grid cell
+    pub fn is_solid(&self, col: usize, row: usize) -> bool {
+        self.get(col, row)
+            .is_some_and(|tile_type| tile_type.collision_filter != 0)
+    }
+
+    /// Returns the rectangle that covers every tile in world coordinates
+    pub fn bounds(&self) -> Rect {
+        Rect::new(
+            0,
+            0,
+            (self.columns as i32 * GRID_SIZE) as u32,
+            (self.rows as i32 * GRID_SIZE) as u32,
+        )
+    }
+
+    /// Returns the rectangle in the level for a grid cell
+    pub fn tile_rect(&self, col: usize, row: usize) -> Rect {
+        Rect::new(
+            col as i32 * GRID_SIZE,
+            row as i32 * GRID_SIZE,
+            GRID_SIZE as u32,
+            GRID_SIZE as u32,
+        )
+    }
+
+    /// Returns the grid cells inside the tilemap that overlap a rectangle
+    pub fn cells_in_rect(&self, rect: &Rect) -> impl Iterator<Item = (usize, usize)> {
+        let cols = cell_range(rect.x(), rect.right(), self.columns);
+        let rows = cell_range(rect.y(), rect.bottom(), self.rows);
+
+        rows.flat_map(move |row| cols.clone().map(move |col| (col, row)))
+    }
+
+    /// Sets the time of the animations of the animated tile types to the
+    /// game clock in `context.time`, so tiles show the same frame as blocks
+    pub fn set_time(&mut self, time: f64) {
+        for animation in self
+            .tile_types
+            .iter_mut()
+            .filter_map(|tile_type| tile_type.animation.as_mut())
+        {
+            animation.set_time(time);
+        }
+    }
+
+    /// Renders the tiles that are inside the viewport
+    pub fn render(
+        &self,
+        viewport: &Viewport,
+        canvas: &mut Canvas<Window>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.render_parallax(viewport, (1.0, 1.0), canvas)
+    }
+
+    /// Renders the tiles that are inside the viewport where the tilemap
+    /// scrolls by a factor of the viewport's scroll
+    pub fn render_parallax(
+        &self,
+        viewport: &Viewport,
+        scroll: (f64, f64),
+        canvas: &mut Canvas<Window>,
+    ) -> Result<(), Box<dyn Error>> {
+        let (origin_x, origin_y) = viewport.parallax_point((0, 0), scroll);
+        let (width, height) = viewport.window_dimensions;
+        let visible = Rect::new(-origin_x, -origin_y, width as u32, height as u32);
+
+        for (col, row) in self.cells_in_rect(&visible) {
+            let Some(tile_type) = self.get(col, row) else {
+                continue;
+            };
+
+            let point = (col as i32 * GRID_SIZE, row as i32 * GRID_SIZE);
+            let (rx, ry) = viewport.parallax_point(point, scroll);
+            let rect = Rect::new(rx, ry, GRID_SIZE as u32, GRID_SIZE as u32);
+            let sprite = match tile_type.animation {
+                Some(ref animation) => Some(animation.current_frame()),
+                None => tile_type.sprite.as_ref(),
+            };
+            match (sprite, tile_type.shape) {
+                (Some(sprite), TileShape::Slope { left, right }) => {
+                    render_slope(sprite, (left, right), rect, canvas)?
+                }
+                (Some(sprite), _) => sprite.render(canvas, rect)?,
+                (None, _) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<Type: Clone> Tilemap<Type> {
+    /// Replaces the sprites of the tiles of a terrain with sprites from the
+    /// spritesheet chosen by the rules based on the neighboring tiles
+    /// of the same terrain. Autotiled tiles don't keep the terrain's animation.
+    pub fn autotile(&mut self, terrain: usize, rules: &AutotileRules, spritesheet: &Spritesheet) {
+        let terrain = self.terrains[terrain];
+        for (i, index) in self.autotile_indices(terrain, rules) {
+            let tile = match self.variants.get(&(terrain, index)) {
+                Some(&tile) => tile,
+                None => {
+                    let tile_type = TileType {
+                        sprite: spritesheet.range(index, index + 1).pop(),
+                        animation: None,
+                        ..self.tile_types[terrain].clone()
+                    };
+                    let tile = self.add_tile_type(tile_type);
+                    self.terrains[tile] = terrain;
+                    self.variants.insert((terrain, index), tile);
+                    tile
+                }
+            };
+            self.tiles[i] = Some(tile);
+        }
+    }
+}
+
+impl<Type: Copy> Tilemap<Type> {
+    /// Returns the actor data of the solid tiles that overlap a rectangle
+    /// so that actors can check for collisions against them
+    pub fn tile_data(&self, rect: &Rect) -> Vec<ActorData<Type>> {
+        self.cells_in_rect(rect)
+            .filter_map(|(col, row)| {
+                let tile_type = self.get(col, row)?;
+                if tile_type.collision_filter == 0 {
+                    return None;
+                }
+
+                let tile_rect = self.tile_rect(col, row);
+                Some(ActorData {
+                    index: TILE_INDEX,
+                    state: 0,
+                    damage: 0,
+                    collision_filter: tile_type.collision_filter,
+                    resolves_collisions: false,
+                    rect: tile_rect,
+                    bounding_box: Some(BoundingBox::Rectangle(SpriteRectangle::from_rect(
+                        tile_rect,
+                    ))),
+                    shape: tile_type.shape,
+                    layers: tile_type.layers,
+                    trigger: false,
+                    actor_type: tile_type.actor_type,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Returns the range of grid cells from a start to an end coordinate
+/// clamped to the number of cells
+fn cell_range(start: i32, end: i32, count: usize) -> Range<usize> {
+    let first = start.div_euclid(GRID_SIZE).max(0) as usize;
+    let last = ((end - 1).div_euclid(GRID_SIZE) + 1).max(0) as usize;
+    first.min(count)..last.min(count)
+}
+
+/// Renders a sprite squashed under the surface of a slope in vertical strips
+/// so that slope tiles look like the slope that actors stand on
+fn render_slope(
+    sprite: &Sprite,
+    (left, right): (f64, f64),
+    rect: Rect,
+    canvas: &mut Canvas<Window>,
+) -> Result<(), Box<dyn Error>> {
+    let (sprite_width, sprite_height) = sprite.size();
+    let size = rect.width() as i32;
+    let scale = f64::from(sprite_width) / f64::from(size);
+
+    for x in (0..size).step_by(SLOPE_STRIP_WIDTH as usize) {
+        let width = SLOPE_STRIP_WIDTH.min(size - x);
+        let middle = (f64::from(x) + f64::from(width) / 2.) / f64::from(size);
+        let height = ((left + (right - left) * middle) * f64::from(rect.height())).round() as i32;
+        if height <= 0 {
+            continue;
+        }
+
+        let src = Rect::new(
+            (f64::from(x) * scale) as i32,
+            0,
+            ((f64::from(width) * scale) as u32).max(1),
+            sprite_height,
+        );
+        if let Some(strip) = sprite.region(src) {
+            let dest = Rect::new(
+                rect.x() + x,
+                rect.bottom() - height,
+                width as u32,
+                height as u32,
+            );
+            strip.render(canvas, dest)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autotile::Neighborhood;
+
+    fn tile(collision_filter: u8) -> TileType<()> {
+        TileType {
+            sprite: None,
+            animation: None,
+            collision_filter,
+            shape: TileShape::Solid,
+            layers: CollisionLayers::default(),
+            actor_type: (),
+        }
+    }
+
+    #[test]
+    fn test_set_and_get() {
+        let mut tilemap = Tilemap::new(4, 3);
+        let solid = tilemap.add_tile_type(tile(0b1111));
+        let decoration = tilemap.add_tile_type(tile(0));
+
+        tilemap.set(1, 2, Some(solid));
+        tilemap.set(2, 2, Some(decoration));
+        tilemap.set(10, 10, Some(solid));
+
+        assert_eq!(tilemap.tile(1, 2), Some(solid));
+        assert_eq!(tilemap.tile(10, 10), None);
+        assert!(tilemap.is_solid(1, 2));
+        assert!(!tilemap.is_solid(2, 2));
+        assert!(!tilemap.is_solid(0, 0));
+        assert_eq!(
+            tilemap.bounds(),
+            Rect::new(0, 0, 4 * GRID_SIZE as u32, 3 * GRID_SIZE as u32)
+        );
+    }
+
+    #[test]
+    fn test_cells_in_rect() {
+        let tilemap: Tilemap<()> = Tilemap::new(4, 3);
+
+        let cells = tilemap
+            .cells_in_rect(&Rect::new(30, 10, 20, 40))
+            .collect::<Vec<_>>();
+        assert_eq!(cells, vec![(0, 0), (1, 0), (0, 1), (1, 1)]);
+
+        // Cells are clamped to the tilemap
+        let cells = tilemap
+            .cells_in_rect(&Rect::new(-100, 100, 1000, 10))
+            .collect::<Vec<_>>();
+        assert_eq!(cells, vec![(0, 2), (1, 2), (2, 2), (3, 2)]);
+
+        assert_eq!(
+            tilemap.cells_in_rect(&Rect::new(-100, 0, 50, 50)).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_tile_data() {
+        let mut tilemap = Tilemap::new(4, 3);
+        let solid = tilemap.add_tile_type(tile(0b1111));
+        let decoration = tilemap.add_tile_type(tile(0));
+        tilemap.set(0, 2, Some(solid));
+        tilemap.set(1, 2, Some(solid));
+        tilemap.set(2, 2, Some(decoration));
+
+        let data = tilemap.tile_data(&Rect::new(0, 40, 120, 41));
+        let rects = data.iter().map(|data| data.rect).collect::<Vec<_>>();
+        assert_eq!(
+            rects,
+            vec![Rect::new(0, 80, 40, 40), Rect::new(40, 80, 40, 40)]
+        );
+        assert!(data.iter().all(|data| data.index == TILE_INDEX));
+    }
+
+    #[test]
+    fn test_autotile_indices() {
+        let mut tilemap = Tilemap::new(3, 3);
+        let ground = tilemap.add_tile_type(tile(0b1111));
+        let stone = tilemap.add_tile_type(tile(0b1111));
+        for col in 0..3 {
+            tilemap.set(col, 1, Some(ground));
+            tilemap.set(col, 2, Some(ground));
+        }
+        tilemap.set(1, 0, Some(stone));
+
+        let mut rules = AutotileRules::new(Neighborhood::FourWay, 14);
+        rules.add_rule(autotile::NORTH, autotile::NORTH, 21);
+
+        // Only ground tiles below other ground tiles are middle tiles
+        let indices = tilemap.autotile_indices(ground, &rules);
+        assert_eq!(
+            indices,
+            vec![(3, 14), (4, 14), (5, 14), (6, 21), (7, 21), (8, 21)]
+        );
+        assert_eq!(tilemap.autotile_indices(stone, &rules), vec![(1, 14)]);
+    }
+}
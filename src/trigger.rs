@@ -0,0 +1,131 @@
+use crate::ActorData;
+use crate::actor_manager::ActorIndex;
+use std::collections::HashMap;
+use std::mem;
+
+/// The change in the overlap of a trigger and an actor since the last frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TriggerEvent {
+    /// The actor started overlapping the trigger this frame
+    Enter,
+    /// The actor overlapped the trigger last frame and still overlaps it
+    Stay,
+    /// The actor stopped overlapping the trigger or one of them was removed
+    Exit,
+}
+
+/// Tracks the actors that overlap trigger volumes from frame to frame
+/// so that triggers get enter, stay and exit events instead of collisions.
+///
+/// Every frame the overlapping pairs are recorded with `overlap` and
+/// then `events` returns the events for the frame.
+pub struct TriggerTracker<Type> {
+    previous: HashMap<(ActorIndex, ActorIndex), (ActorData<Type>, ActorData<Type>)>,
+    current: HashMap<(ActorIndex, ActorIndex), (ActorData<Type>, ActorData<Type>)>,
+}
+
+impl<Type: Copy> TriggerTracker<Type> {
+    pub fn new() -> TriggerTracker<Type> {
+        TriggerTracker {
+            previous: HashMap::new(),
+            current: HashMap::new(),
+        }
+    }
+
+    /// Records that an actor overlaps a trigger in the current frame.
+    /// Recording the same pair more than once in a frame has no effect.
+    pub fn overlap(&mut self, trigger: &ActorData<Type>, actor: &ActorData<Type>) {
+        self.current
+            .insert((trigger.index, actor.index), (*trigger, *actor));
+    }
+
+    /// Returns true if an actor overlapped a trigger in the last frame
+    pub fn overlapping(&self, trigger: ActorIndex, actor: ActorIndex) -> bool {
+        self.previous.contains_key(&(trigger, actor))
+    }
+
+    /// Ends the current frame and returns the trigger, the actor and the event
+    /// for every pair that overlaps now or overlapped in the last frame.
+    ///
+    /// Exit events have the actor data from the last frame that the pair overlapped.
+    pub fn events(&mut self) -> Vec<(ActorData<Type>, ActorData<Type>, TriggerEvent)> {
+        let mut events: Vec<_> = self
+            .current
+            .iter()
+            .map(|(key, &(trigger, actor))| {
+                let event = if self.previous.contains_key(key) {
+                    TriggerEvent::Stay
+                } else {
+                    TriggerEvent::Enter
+                };
+                (trigger, actor, event)
+            })
+            .chain(
+                self.previous
+                    .iter()
+                    .filter(|(key, _)| !self.current.contains_key(key))
+                    .map(|(_, &(trigger, actor))| (trigger, actor, TriggerEvent::Exit)),
+            )
+            .collect();
+        events.sort_by_key(|(trigger, actor, _)| (trigger.index, actor.index));
+
+        self.previous = mem::take(&mut self.current);
+        events
+    }
+}
+
+impl<Type: Copy> Default for TriggerTracker<Type> {
+    fn default() -> TriggerTracker<Type> {
+        TriggerTracker::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::actor_data;
+    use sdl2::rect::Rect;
+
+    fn event_ids(
+        events: &[(ActorData<()>, ActorData<()>, TriggerEvent)],
+    ) -> Vec<(usize, TriggerEvent)> {
+        events
+            .iter()
+            .map(|(_, actor, event)| (actor.index.id, *event))
+            .collect()
+    }
+
+    #[test]
+    fn test_trigger_events() {
+        let rect = Rect::new(0, 0, 10, 10);
+        let zone = ActorData {
+            trigger: true,
+            ..actor_data(0, rect)
+        };
+        let (player, enemy) = (actor_data(1, rect), actor_data(2, rect));
+        let mut tracker = TriggerTracker::new();
+
+        tracker.overlap(&zone, &player);
+        tracker.overlap(&zone, &player);
+        let events = tracker.events();
+        assert_eq!(event_ids(&events), vec![(1, TriggerEvent::Enter)]);
+        assert_eq!(events[0].0.index, zone.index);
+        assert!(tracker.overlapping(zone.index, player.index));
+
+        tracker.overlap(&zone, &player);
+        tracker.overlap(&zone, &enemy);
+        assert_eq!(
+            event_ids(&tracker.events()),
+            vec![(1, TriggerEvent::Stay), (2, TriggerEvent::Enter)]
+        );
+
+        tracker.overlap(&zone, &enemy);
+        assert_eq!(
+            event_ids(&tracker.events()),
+            vec![(1, TriggerEvent::Exit), (2, TriggerEvent::Stay)]
+        );
+
+        assert_eq!(event_ids(&tracker.events()), vec![(2, TriggerEvent::Exit)]);
+        assert!(tracker.events().is_empty());
+    }
+}
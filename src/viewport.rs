@@ -2,6 +2,10 @@ use crate::collision::center_point;
 use crate::context::Window;
 use sdl2::rect::Rect;
 
+/// The distance in pixels past the top and left of the window
+/// where actors still count as being in the viewport
+pub const VIEWPORT_MARGIN: i32 = 32;
+
 /// Calculates the origin coordinate for the viewport
 /// given the center coordinate, the canvas coordinate, and the map coordinate
 fn calc_viewport_point(center_coord: f64, window_coord: f64, map_coord: f64) -> f64 {
@@ -53,7 +57,7 @@ impl Viewport {
 
     /// Returns true if the point is inside the viewport, false otherwise
     pub fn in_viewport(&self, point: (i32, i32)) -> bool {
-        let margin = 32;
+        let margin = VIEWPORT_MARGIN;
 
         let (v_min_x, v_max_x) = (self.x - margin, self.x + self.window_dimensions.0);
         let (v_min_y, v_max_y) = (self.y - margin, self.y + self.window_dimensions.1);
@@ -66,6 +70,15 @@ impl Viewport {
         (map_point.0 - self.x, map_point.1 - self.y)
     }
 
+    /// Returns the point in a layer relative to the viewpoint where the layer
+    /// scrolls by a factor of the viewport's scroll in the x and y axis
+    pub fn parallax_point(&self, map_point: (i32, i32), scroll: (f64, f64)) -> (i32, i32) {
+        (
+            map_point.0 - (f64::from(self.x) * scroll.0) as i32,
+            map_point.1 - (f64::from(self.y) * scroll.1) as i32,
+        )
+    }
+
     /// Returns true if the rectangle is inside the viewport, false otherwise
     pub fn rect_in_viewport(&self, rect: &Rect) -> bool {
         let x_plus_width = rect.x() + rect.width() as i32;
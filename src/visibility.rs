@@ -0,0 +1,142 @@
+use crate::raycast::{self, Polygon, Segment};
+use crate::vector::Vector2D;
+use sdl2::rect::Rect;
+use std::f64::consts::PI;
+
+/// The angle in radians that the rays are cast to each side of a corner
+/// so that they pass the corner and hit whatever is behind it
+const CORNER_OFFSET: f64 = 0.0001;
+
+/// Returns true if none of the polygons block the line between two points,
+/// like checking if an enemy can see the player.
+///
+/// Like `World::line_of_sight`, the polygon that the line starts in doesn't
+/// block it. Use `World::line_of_sight` to look past the actors and tiles of
+/// a world, which also sees through triggers and the bottom of one-way platforms.
+pub fn line_of_sight<P: Polygon>(from: (f64, f64), to: (f64, f64), polygons: &[P]) -> bool {
+    let line = Segment {
+        point: from,
+        vector: Vector2D {
+            x: to.0 - from.0,
+            y: to.1 - from.1,
+        },
+    };
+
+    !polygons
+        .iter()
+        .any(|polygon| raycast::cast_ray_entering(&line, polygon).is_some())
+}
+
+/// Returns the points of the polygon that can be seen from an eye point,
+/// sorted by their angle around the eye, for fog of war or lighting.
+///
+/// The polygons block the view and the bounds limit how far the eye can see,
+/// so the eye has to be inside of the bounds. A ray is cast to every corner
+/// of the polygons and just past each side of it, so this takes time
+/// proportional to the number of corners times the number of sides.
+pub fn visibility_polygon<P: Polygon>(
+    eye: (f64, f64),
+    polygons: &[P],
+    bounds: &Rect,
+) -> Vec<(f64, f64)> {
+    let mut sides = bounds.sides();
+    for polygon in polygons {
+        sides.extend(polygon.sides());
+    }
+
+    let mut angles = Vec::with_capacity(sides.len() * 3);
+    for side in &sides {
+        let angle = (side.point.1 - eye.1).atan2(side.point.0 - eye.0);
+        angles.extend([angle - CORNER_OFFSET, angle, angle + CORNER_OFFSET]);
+    }
+    angles.sort_by(|a, b| a.total_cmp(b));
+    angles.dedup();
+
+    // long enough to reach the bounds from anywhere inside of them
+    let length = f64::from(bounds.width() + bounds.height()) * 2.;
+    let mut points: Vec<(f64, f64)> = Vec::with_capacity(angles.len());
+    for angle in angles {
+        // keep the angles in order when they wrap around
+        let angle = angle.clamp(-PI, PI);
+        let ray = Segment {
+            point: eye,
+            vector: Vector2D {
+                x: angle.cos() * length,
+                y: angle.sin() * length,
+            },
+        };
+
+        let closest = sides
+            .iter()
+            .filter_map(|side| ray.intersects(side))
+            .min_by(|a, b| distance(eye, *a).total_cmp(&distance(eye, *b)));
+        if let Some(point) = closest
+            && points
+                .last()
+                .is_none_or(|&last| distance(last, point) > 1e-6)
+        {
+            points.push(point);
+        }
+    }
+
+    points
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: (f64, f64), b: (f64, f64)) -> bool {
+        distance(a, b) < 0.1
+    }
+
+    #[test]
+    fn test_line_of_sight() {
+        let walls = [Rect::new(40, 0, 20, 40), Rect::new(40, 80, 20, 20)];
+
+        assert!(line_of_sight((10., 60.), (90., 60.), &walls));
+        assert!(!line_of_sight((10., 20.), (90., 20.), &walls));
+        assert!(!line_of_sight((10., 95.), (90., 80.), &walls));
+        assert!(line_of_sight((10., 10.), (30., 90.), &walls));
+        // Eyes inside of a wall can see out of it
+        assert!(line_of_sight((50., 20.), (90., 20.), &walls));
+        assert!(!line_of_sight((50., 20.), (50., 90.), &walls));
+    }
+
+    #[test]
+    fn test_visibility_polygon_empty() {
+        let bounds = Rect::new(0, 0, 100, 100);
+        let points = visibility_polygon::<Rect>((30., 60.), &[], &bounds);
+
+        for corner in [(0., 0.), (100., 0.), (100., 100.), (0., 100.)] {
+            assert!(points.iter().any(|&point| close(point, corner)));
+        }
+        for &(x, y) in &points {
+            assert!(x.abs() < 1e-6 || y.abs() < 1e-6 || x > 99.99 || y > 99.99);
+        }
+    }
+
+    #[test]
+    fn test_visibility_polygon_shadow() {
+        let bounds = Rect::new(0, 0, 100, 100);
+        let block = Rect::new(70, 40, 10, 20);
+        let points = visibility_polygon((50., 50.), &[block], &bounds);
+
+        // The near corners of the block are visible and
+        // the right side of the bounds behind the block isn't
+        assert!(points.iter().any(|&point| close(point, (70., 40.))));
+        assert!(points.iter().any(|&point| close(point, (70., 60.))));
+        assert!(!points.iter().any(|&(x, y)| x > 99. && y > 41. && y < 59.));
+
+        // The points are sorted by angle around the eye
+        let angles: Vec<_> = points
+            .iter()
+            .map(|&(x, y)| (y - 50.).atan2(x - 50.))
+            .collect();
+        assert!(angles.windows(2).all(|pair| pair[0] <= pair[1] + 1e-9));
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::time::SystemTime;
+
+/// Watches files for changes by polling their modification times
+pub struct FileWatcher {
+    /// The last modification time for every watched path
+    files: HashMap<String, Option<SystemTime>>,
+    /// The watched directories that are searched for new files
+    dirs: Vec<String>,
+    /// Time in seconds between checking the files
+    interval: f64,
+    /// Time in seconds since the files were last checked
+    time_since_poll: f64,
+}
+
+impl FileWatcher {
+    /// Creates a watcher that checks the files every interval in seconds
+    pub fn new(interval: f64) -> FileWatcher {
+        FileWatcher {
+            files: HashMap::new(),
+            dirs: Vec::new(),
+            interval,
+            time_since_poll: 0.0,
+        }
+    }
+
+    /// Starts watching a file for changes
+    pub fn watch(&mut self, path: &str) {
+        self.files.insert(path.to_owned(), modified_time(path));
+    }
+
+    /// Starts watching every file inside a directory and its subdirectories
+    /// for changes, including the files that are created later
+    pub fn watch_dir(&mut self, path: &str) -> io::Result<()> {
+        let mut files = Vec::new();
+        files_in_dir(path, &mut files)?;
+        for file in files {
+            self.watch(&file);
+        }
+
+        self.dirs.push(path.to_owned());
+        Ok(())
+    }
+
+    /// Stops watching a file for changes
+    pub fn unwatch(&mut self, path: &str) {
+        self.files.remove(path);
+    }
+
+    /// Called every frame with the elapsed time and returns the paths
+    /// of the files that changed once the interval has passed
+    pub fn update(&mut self, elapsed: f64) -> Vec<String> {
+        self.time_since_poll += elapsed;
+        if self.time_since_poll < self.interval {
+            return Vec::new();
+        }
+
+        self.time_since_poll = 0.0;
+        self.poll()
+    }
+
+    /// Checks the watched files and returns the paths of
+    /// the files that changed since the last check
+    pub fn poll(&mut self) -> Vec<String> {
+        let mut changed = Vec::new();
+        for dir in &self.dirs {
+            let mut files = Vec::new();
+            // a directory that can't be read right now is checked again next time
+            let _ = files_in_dir(dir, &mut files);
+            for file in files {
+                if !self.files.contains_key(&file) {
+                    self.files.insert(file.clone(), modified_time(&file));
+                    changed.push(file);
+                }
+            }
+        }
+
+        for (path, last_modified) in self.files.iter_mut() {
+            let modified = modified_time(path);
+            if modified != *last_modified {
+                *last_modified = modified;
+                changed.push(path.clone());
+            }
+        }
+
+        changed
+    }
+}
+
+/// Adds the paths of the files inside a directory and its subdirectories
+fn files_in_dir(path: &str, files: &mut Vec<String>) -> io::Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if let Some(path) = entry.path().to_str() {
+            if file_type.is_dir() {
+                files_in_dir(path, files)?;
+            } else if file_type.is_file() {
+                files.push(path.to_owned());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the modification time of a file or None if it can't be read
+fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::File;
+    use std::path::PathBuf;
+    use std::process;
+    use std::time::Duration;
+
+    /// Returns a path in the temp directory that no other test or test run uses
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("mold2d_watcher_{}_{}", process::id(), name))
+    }
+
+    #[test]
+    fn test_poll_changed_file() {
+        let path = temp_path("changed.txt");
+        let path_str = path.to_str().unwrap();
+        fs::write(&path, "a").unwrap();
+
+        let mut watcher = FileWatcher::new(1.0);
+        watcher.watch(path_str);
+        assert!(watcher.poll().is_empty());
+
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+
+        assert!(watcher.update(0.5).is_empty());
+        assert_eq!(watcher.update(0.5), vec![path_str.to_owned()]);
+        assert!(watcher.poll().is_empty());
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(watcher.poll(), vec![path_str.to_owned()]);
+    }
+
+    #[test]
+    fn test_watch_dir() {
+        let dir = temp_path("dir");
+        let nested = dir.join("nested");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("a.png"), "a").unwrap();
+
+        let mut watcher = FileWatcher::new(1.0);
+        watcher.watch_dir(dir.to_str().unwrap()).unwrap();
+        assert!(watcher.poll().is_empty());
+
+        // Files created after watching the directory are picked up
+        let created = nested.join("b.png");
+        fs::write(&created, "b").unwrap();
+        assert_eq!(watcher.poll(), vec![created.to_str().unwrap().to_owned()]);
+        assert!(watcher.poll().is_empty());
+
+        let file = File::options().write(true).open(&created).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(watcher.poll(), vec![created.to_str().unwrap().to_owned()]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
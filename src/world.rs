@@ -0,0 +1,776 @@
+use crate::actor_manager::{ActorIndex, ActorManager};
+use crate::collision::{self, BoundingBox, CollisionSide, TileShape};
+use crate::context::Context;
+use crate::raycast::{self, RayHit, Segment, SweepHit};
+use crate::spatial::{Broadphase, QueryFilter, SpatialIndex};
+use crate::sprite::SpriteRectangle;
+use crate::tilemap::Tilemap;
+use crate::trigger::{TriggerEvent, TriggerTracker};
+use crate::vector::{PositionChange, Vector2D};
+use crate::viewport::{VIEWPORT_MARGIN, Viewport};
+use crate::{Actor, ActorData};
+use sdl2::rect::Rect;
+use std::collections::HashSet;
+
+/// What happened between the two actors of a pair
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PairEvent {
+    /// The actor collided into the other actor on a side of the actor
+    Collision(CollisionSide),
+    /// The other actor entered, stayed in or exited the actor,
+    /// which is a trigger volume
+    Trigger(TriggerEvent),
+}
+
+/// Two actors, or an actor and a tile, that collided or overlapped in a step
+#[derive(Clone, Copy, PartialEq)]
+pub struct CollisionPair<Type> {
+    /// The actor that moved, or the trigger for trigger events.
+    /// For collisions this is the data from before the actor moved.
+    pub actor: ActorData<Type>,
+    /// The actor or tile that the actor collided with,
+    /// or the actor in the trigger for trigger events
+    pub other: ActorData<Type>,
+    pub event: PairEvent,
+}
+
+/// The closest actor or tile that a ray hits
+#[derive(Clone, Copy, PartialEq)]
+pub struct RaycastHit<Type> {
+    /// The actor or tile that was hit. Tiles have the tile index.
+    pub actor: ActorData<Type>,
+    /// The point where the ray hits the actor
+    pub point: (f64, f64),
+    /// The unit normal of the side that was hit pointing out of the actor
+    pub normal: Vector2D,
+    /// The distance from the start of the ray to the point
+    pub distance: f64,
+}
+
+/// The first actor or tile that a shape hits when it is swept along a movement
+#[derive(Clone, Copy, PartialEq)]
+pub struct ShapeCastHit<Type> {
+    /// The actor or tile that was hit. Tiles have the tile index.
+    pub actor: ActorData<Type>,
+    /// The fraction of the movement from 0 to 1 before the hit
+    pub time: f64,
+    /// The unit normal of the side that was hit pointing out of the actor
+    pub normal: Vector2D,
+}
+
+/// Moves an actor by a position change, usually by sending it a message
+pub type MoveActor<A> = dyn Fn(&mut A, PositionChange);
+
+/// Handler for a view to send the messages for a collision pair
+pub type PairHandler<A> =
+    dyn Fn(&CollisionPair<<A as Actor>::Type>, &mut ActorManager<A>, &mut Viewport, &mut Context);
+
+/// Moves the actors and finds the collisions between them every frame.
+///
+/// A step runs the same pipeline for every game: the broadphase keeps a
+/// spatial index of every actor to find the actors near each other, the
+/// narrowphase checks the nearby actors and tiles for collisions and
+/// resolves them, and the pairs are dispatched to the game to send messages.
+pub struct World<Type> {
+    broadphase: Broadphase,
+    spatial_index: Box<dyn SpatialIndex<Type>>,
+    /// The actors in the spatial index, compared against the actors
+    /// in the actor manager to find the spawned and removed actors
+    spawned: HashSet<ActorIndex>,
+    triggers: TriggerTracker<Type>,
+    step_height: i32,
+}
+
+impl<Type: Copy + PartialEq + 'static> World<Type> {
+    pub fn new(bounds: Rect, broadphase: Broadphase) -> World<Type> {
+        World {
+            broadphase,
+            spatial_index: broadphase.create(bounds),
+            spawned: HashSet::new(),
+            triggers: TriggerTracker::new(),
+            step_height: 0,
+        }
+    }
+
+    /// Sets the height of the blocks that actors on a slope
+    /// walk onto instead of bumping into
+    pub fn with_step_height(mut self, step_height: i32) -> World<Type> {
+        self.step_height = step_height;
+        self
+    }
+
+    /// Removes every actor and trigger overlap, like when loading a new level
+    pub fn reset(&mut self, bounds: Rect) {
+        self.spatial_index = self.broadphase.create(bounds);
+        self.spawned.clear();
+        self.triggers = TriggerTracker::new();
+    }
+
+    /// Returns the spatial index with every actor
+    /// for queries outside of the step
+    pub fn spatial_index(&self) -> &dyn SpatialIndex<Type> {
+        self.spatial_index.as_ref()
+    }
+
+    /// Casts a ray against the actors in the spatial index and the tiles
+    /// and returns the closest hit on one of the layers in the filter.
+    ///
+    /// Rays pass through triggers and the actors and tiles that they start in,
+    /// and one-way platforms are only hit from above.
+    pub fn raycast(
+        &self,
+        ray: &Segment,
+        tilemap: &Tilemap<Type>,
+        filter: &QueryFilter<Type>,
+    ) -> Option<RaycastHit<Type>> {
+        self.candidates(&segment_bounds(ray), tilemap, filter)
+            .into_iter()
+            .filter(|actor| !actor.trigger)
+            .filter_map(|actor| {
+                let hit = cast_ray_actor(ray, &actor)?;
+                Some(RaycastHit {
+                    actor,
+                    point: hit.point,
+                    normal: hit.normal,
+                    distance: hit.distance,
+                })
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    /// Returns true if no actor or tile on one of the layers in the filter
+    /// blocks the line between two points. The filter should leave out
+    /// the actor that is looking and the actor that it is looking at.
+    pub fn line_of_sight(
+        &self,
+        from: (f64, f64),
+        to: (f64, f64),
+        tilemap: &Tilemap<Type>,
+        filter: &QueryFilter<Type>,
+    ) -> bool {
+        let ray = Segment {
+            point: from,
+            vector: Vector2D {
+                x: to.0 - from.0,
+                y: to.1 - from.1,
+            },
+        };
+        self.raycast(&ray, tilemap, filter).is_none()
+    }
+
+    /// Sweeps a shape along a movement vector against the actors in the
+    /// spatial index and the tiles and returns the first hit on one of the
+    /// layers in the filter, like checking if an actor fits somewhere.
+    ///
+    /// Shapes pass through triggers, and one-way platforms
+    /// are only hit from above by shapes that aren't in them.
+    pub fn shape_cast(
+        &self,
+        shape: &BoundingBox,
+        movement: &Vector2D,
+        tilemap: &Tilemap<Type>,
+        filter: &QueryFilter<Type>,
+    ) -> Option<ShapeCastHit<Type>> {
+        let rect = shape.bounding_rect();
+        let mut moved_rect = rect;
+        moved_rect.offset(movement.x.round() as i32, movement.y.round() as i32);
+
+        self.candidates(&rect.union(moved_rect), tilemap, filter)
+            .into_iter()
+            .filter(|actor| !actor.trigger)
+            .filter_map(|actor| {
+                let hit = cast_shape_actor(shape, movement, &actor)?;
+                Some(ShapeCastHit {
+                    actor,
+                    time: hit.time,
+                    normal: hit.normal,
+                })
+            })
+            .min_by(|a, b| a.time.total_cmp(&b.time))
+    }
+
+    /// Returns the actors in the spatial index and the tiles
+    /// that overlap a rectangle and pass the filter
+    fn candidates(
+        &self,
+        rect: &Rect,
+        tilemap: &Tilemap<Type>,
+        filter: &QueryFilter<Type>,
+    ) -> Vec<ActorData<Type>> {
+        let mut candidates: Vec<_> = self
+            .spatial_index
+            .query_rect(rect, filter)
+            .into_iter()
+            .cloned()
+            .collect();
+        candidates.extend(
+            tilemap
+                .tile_data(rect)
+                .into_iter()
+                .filter(|tile| filter.matches(tile)),
+        );
+        candidates
+    }
+
+    /// Updates the actors in the viewport, moves them, resolves their
+    /// collisions with the other actors and the tiles and calls the handler
+    /// with every collision pair.
+    ///
+    /// The collisions of an actor are handled before the next actor is updated,
+    /// and the trigger events are handled after every actor is updated.
+    /// Returns every pair in the order that they were handled.
+    ///
+    /// The actors of a pair are moved in the spatial index after the handler
+    /// in case it moved them. Other actors that the game moves outside of
+    /// an update keep their old position in the index until they are updated.
+    #[allow(clippy::too_many_arguments)]
+    pub fn step<A>(
+        &mut self,
+        actors: &mut ActorManager<A>,
+        tilemap: &Tilemap<Type>,
+        viewport: &mut Viewport,
+        context: &mut Context,
+        elapsed: f64,
+        move_actor: &MoveActor<A>,
+        handler: &PairHandler<A>,
+    ) -> Vec<CollisionPair<Type>>
+    where
+        A: Actor<Type = Type> + ?Sized,
+    {
+        let keys = self.sync(actors, viewport);
+
+        let mut pairs = Vec::new();
+        for key in keys {
+            let Some(actor) = actors.get_mut(key) else {
+                continue;
+            };
+            let data = actor.data();
+
+            // update the actor
+            let pos_change = actor.update(context, elapsed);
+
+            // stop fast actors at the first block in their path
+            // so that they don't move through thin blocks
+            let mut moved_rect = data.rect;
+            moved_rect.offset(pos_change.x, pos_change.y);
+            let path = tilemap.tile_data(&data.rect.union(moved_rect));
+            move_actor(actor, collision::sweep_collision(&data, &pos_change, &path));
+
+            let mut collisions = Vec::new();
+            if data.collision_filter != 0 && data.layers.mask != 0 {
+                let nearby = self.nearby(key, &actor.data(), tilemap);
+                collisions = self.narrowphase(actor, &data.rect, nearby, move_actor);
+            }
+            self.spatial_index.update(actor.data());
+
+            for (other, side) in collisions {
+                let pair = CollisionPair {
+                    actor: data,
+                    other,
+                    event: PairEvent::Collision(side),
+                };
+                handler(&pair, actors, viewport, context);
+                self.refresh(actors, key);
+                self.refresh(actors, other.index);
+                pairs.push(pair);
+            }
+        }
+
+        for (trigger, actor, event) in self.triggers.events() {
+            let pair = CollisionPair {
+                actor: trigger,
+                other: actor,
+                event: PairEvent::Trigger(event),
+            };
+            handler(&pair, actors, viewport, context);
+            self.refresh(actors, trigger.index);
+            self.refresh(actors, actor.index);
+            pairs.push(pair);
+        }
+
+        pairs
+    }
+
+    /// Inserts the actors spawned since the last step into the spatial index,
+    /// removes the actors that were removed and returns the actors in the
+    /// viewport in index order.
+    ///
+    /// Every spawned actor is in the spatial index so actors can find the
+    /// actors near them even outside of the viewport. Actors only move when
+    /// they are updated or get a message from a pair, which moves them in the
+    /// index, so the actors that didn't change aren't touched.
+    fn sync<A>(&mut self, actors: &mut ActorManager<A>, viewport: &Viewport) -> Vec<ActorIndex>
+    where
+        A: Actor<Type = Type> + ?Sized,
+    {
+        let mut alive = HashSet::with_capacity(self.spawned.len());
+        for (key, actor) in actors.iter_mut() {
+            if !self.spawned.contains(&key) {
+                self.spatial_index.insert(actor.data());
+            }
+            alive.insert(key);
+        }
+        for &key in self.spawned.difference(&alive) {
+            self.spatial_index.remove(key);
+        }
+        self.spawned = alive;
+
+        // the viewport includes a margin around the window
+        // and one more pixel so that touching actors are found
+        let (width, height) = viewport.window_dimensions;
+        let area = Rect::new(
+            viewport.x - VIEWPORT_MARGIN - 1,
+            viewport.y - VIEWPORT_MARGIN - 1,
+            (width + VIEWPORT_MARGIN + 2) as u32,
+            (height + VIEWPORT_MARGIN + 2) as u32,
+        );
+        let mut keys: Vec<_> = self
+            .spatial_index
+            .query_rect(&area, &QueryFilter::new())
+            .into_iter()
+            .filter(|data| viewport.rect_in_viewport(&data.rect))
+            .map(|data| data.index)
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Moves an actor in the spatial index after a handler
+    /// may have moved it by sending it a message
+    fn refresh<A>(&mut self, actors: &mut ActorManager<A>, index: ActorIndex)
+    where
+        A: Actor<Type = Type> + ?Sized,
+    {
+        if let Some(actor) = actors.get_mut(index) {
+            self.spatial_index.update(actor.data());
+        }
+    }
+
+    /// Returns the actors near a moved actor and the tiles touching or just
+    /// below it that are on the layers the actor collides with
+    fn nearby(
+        &self,
+        index: ActorIndex,
+        moved: &ActorData<Type>,
+        tilemap: &Tilemap<Type>,
+    ) -> Vec<ActorData<Type>> {
+        let mut nearby = self
+            .spatial_index
+            .retrieve(&moved.rect)
+            .into_iter()
+            .cloned()
+            .filter(|other| other.index != index)
+            .collect::<Vec<_>>();
+        let touching_rect = Rect::new(
+            moved.rect.x() - 1,
+            moved.rect.y() - 1,
+            moved.rect.width() + 2,
+            moved.rect.height() + 1 + self.step_height as u32,
+        );
+        nearby.extend(tilemap.tile_data(&touching_rect));
+
+        nearby.retain(|other| moved.layers.collides_with(&other.layers));
+        nearby
+    }
+
+    /// Records the triggers the actor overlaps and resolves the collisions
+    /// with the rest of the nearby actors. Returns the collided actors
+    /// with the side of the actor that they collided on.
+    fn narrowphase<A>(
+        &mut self,
+        actor: &mut A,
+        prev: &Rect,
+        mut nearby: Vec<ActorData<Type>>,
+        move_actor: &MoveActor<A>,
+    ) -> Vec<(ActorData<Type>, CollisionSide)>
+    where
+        A: Actor<Type = Type> + ?Sized,
+    {
+        // triggers never resolve collisions so only
+        // record that the actor overlaps them
+        let moved = actor.data();
+        for other in &nearby {
+            if (moved.trigger || other.trigger) && actor.collides_with(other).is_some() {
+                if other.trigger {
+                    self.triggers.overlap(other, &moved);
+                } else {
+                    self.triggers.overlap(&moved, other);
+                }
+            }
+        }
+        nearby.retain(|other| !moved.trigger && !other.trigger);
+
+        resolve_collisions(actor, prev, nearby, self.step_height, move_actor)
+    }
+}
+
+/// Moves an actor out of the actors it collided with and onto the platforms
+/// and slopes it landed on. Returns the collided actors with the side
+/// of the actor that they collided on.
+fn resolve_collisions<A>(
+    actor: &mut A,
+    prev: &Rect,
+    mut nearby: Vec<ActorData<A::Type>>,
+    step_height: i32,
+    move_actor: &MoveActor<A>,
+) -> Vec<(ActorData<A::Type>, CollisionSide)>
+where
+    A: Actor + ?Sized,
+    A::Type: Copy,
+{
+    // land on platforms and slopes first so that actors walking
+    // up a slope don't get stuck on the blocks at the top of it
+    nearby.sort_by_key(|other| other.shape == TileShape::Solid);
+
+    let mut collisions = Vec::new();
+    let mut on_slope = false;
+    for other in nearby {
+        if other.shape != TileShape::Solid {
+            let touching =
+                other.shape != TileShape::OneWay || actor.collides_with(&other).is_some();
+            if touching && land(actor, &other, prev, move_actor) {
+                on_slope |= other.shape != TileShape::OneWay;
+                collisions.push((other, CollisionSide::Bottom));
+            }
+        } else if let Some(side) = actor.collides_with(&other) {
+            let horizontal = side == CollisionSide::Left || side == CollisionSide::Right;
+            let step = other.rect.top() >= actor.data().rect.bottom() - step_height;
+            if on_slope && horizontal && step {
+                continue;
+            }
+
+            if let Some(change) = collision::resolve_collision(&actor.data(), &other) {
+                move_actor(actor, change);
+            }
+            collisions.push((other, side));
+        }
+    }
+    collisions
+}
+
+/// Moves an actor onto a one-way platform or slope it landed on.
+/// Returns true if the actor is standing on the platform or slope.
+fn land<A>(
+    actor: &mut A,
+    other: &ActorData<A::Type>,
+    prev: &Rect,
+    move_actor: &MoveActor<A>,
+) -> bool
+where
+    A: Actor + ?Sized,
+{
+    let data = actor.data();
+    if !data.resolves_collisions {
+        return false;
+    }
+
+    match other.shape.landing(&other.rect, prev, &data.rect) {
+        Some(distance) => {
+            if distance != 0 {
+                move_actor(actor, PositionChange::new().up(distance));
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Returns the rectangle that contains a segment
+fn segment_bounds(ray: &Segment) -> Rect {
+    let (x1, y1) = ray.point;
+    let (x2, y2) = (x1 + ray.vector.x, y1 + ray.vector.y);
+    let (left, top) = (x1.min(x2).floor() as i32, y1.min(y2).floor() as i32);
+    let (right, bottom) = (x1.max(x2).ceil() as i32, y1.max(y2).ceil() as i32);
+    Rect::new(
+        left,
+        top,
+        (right - left + 1) as u32,
+        (bottom - top + 1) as u32,
+    )
+}
+
+/// Casts a ray against the shape of an actor or tile.
+/// Rays that start inside the shape pass through it.
+fn cast_ray_actor<Type>(ray: &Segment, actor: &ActorData<Type>) -> Option<RayHit> {
+    let hit = match (actor.shape.polygon(&actor.rect), actor.bounding_box) {
+        (Some(polygon), _) => raycast::cast_ray_entering(ray, &polygon),
+        (None, Some(bbox)) => raycast::cast_ray_entering(ray, &bbox),
+        (None, None) => raycast::cast_ray_entering(ray, &actor.rect),
+    }?;
+
+    if actor.shape == TileShape::OneWay && hit.side != Some(CollisionSide::Top) {
+        return None;
+    }
+    Some(hit)
+}
+
+/// Sweeps a shape against the shape of an actor or tile
+fn cast_shape_actor<Type>(
+    shape: &BoundingBox,
+    movement: &Vector2D,
+    actor: &ActorData<Type>,
+) -> Option<SweepHit> {
+    let other = match actor.shape.polygon(&actor.rect) {
+        Some(polygon) => BoundingBox::ConvexPolygon(polygon),
+        None => actor
+            .bounding_box
+            .unwrap_or(BoundingBox::Rectangle(SpriteRectangle::from_rect(
+                actor.rect,
+            ))),
+    };
+    let hit = raycast::cast_shape(shape, movement, &other)?;
+
+    let from_above = hit.time > 0. && hit.side == Some(CollisionSide::Top);
+    if actor.shape == TileShape::OneWay && !from_above {
+        return None;
+    }
+    Some(hit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision::CollisionLayers;
+    use crate::context;
+    use crate::level::GRID_SIZE;
+    use crate::test_utils::{TestActor, actor_data};
+    use crate::tilemap::TileType;
+
+    fn tile(id: usize, rect: Rect, shape: TileShape) -> ActorData<()> {
+        ActorData {
+            shape,
+            ..actor_data(id, rect)
+        }
+    }
+
+    fn move_actor(actor: &mut TestActor, change: PositionChange) {
+        actor.rect.offset(change.x, change.y);
+    }
+
+    #[test]
+    fn test_resolve_collisions() {
+        // An actor that fell into the ground is pushed back on top of it
+        let mut actor = TestActor {
+            index: ActorIndex {
+                id: 0,
+                generation: 0,
+            },
+            rect: Rect::new(10, 35, 20, 20),
+        };
+        let ground = tile(1, Rect::new(0, 50, 40, 40), TileShape::Solid);
+        let prev = Rect::new(10, 25, 20, 20);
+        let collisions = resolve_collisions(&mut actor, &prev, vec![ground], 0, &move_actor);
+
+        assert_eq!(actor.rect, Rect::new(10, 30, 20, 20));
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].0.index.id, 1);
+        assert_eq!(collisions[0].1, CollisionSide::Bottom);
+    }
+
+    #[test]
+    fn test_resolve_collisions_on_slope() {
+        // An actor walking up a slope lands on it first and
+        // walks onto the block at the top of the slope
+        let mut actor = TestActor {
+            index: ActorIndex {
+                id: 0,
+                generation: 0,
+            },
+            rect: Rect::new(25, 12, 20, 20),
+        };
+        let slope = tile(
+            1,
+            Rect::new(0, 0, 40, 40),
+            TileShape::Slope {
+                left: 0.0,
+                right: 1.0,
+            },
+        );
+        let block = tile(2, Rect::new(40, 28, 40, 40), TileShape::Solid);
+        let prev = Rect::new(20, 10, 20, 20);
+        let collisions = resolve_collisions(&mut actor, &prev, vec![block, slope], 8, &move_actor);
+
+        assert_eq!(collisions[0].0.index.id, 1);
+        assert_eq!(collisions[0].1, CollisionSide::Bottom);
+        assert!(collisions.iter().all(|(other, side)| {
+            other.index.id != 2 || (*side != CollisionSide::Left && *side != CollisionSide::Right)
+        }));
+    }
+
+    #[test]
+    fn test_sync() {
+        let window = context::Window {
+            title: "test",
+            width: 400,
+            height: 200,
+        };
+        let viewport = Viewport::new(&window, (2000, 2000));
+        let mut world = World::new(Rect::new(0, 0, 2000, 2000), Broadphase::Quadtree);
+        let mut actors: ActorManager<TestActor> = ActorManager::new();
+        let spawn = |actors: &mut ActorManager<TestActor>, x, y| {
+            let next_index = actors.next_index();
+            let index = next_index.index();
+            let rect = Rect::new(x, y, 20, 20);
+            actors.add(next_index, Box::new(TestActor { index, rect }));
+            index
+        };
+
+        let player = spawn(&mut actors, 100, 100);
+        let enemy = spawn(&mut actors, 300, 100);
+        let far = spawn(&mut actors, 1500, 1500);
+        assert_eq!(world.sync(&mut actors, &viewport), vec![player, enemy]);
+        assert_eq!(world.spatial_index().len(), 3);
+
+        // Removed actors leave the index and spawned actors join it
+        actors.remove(enemy);
+        let coin = spawn(&mut actors, 200, 150);
+        assert_eq!(world.sync(&mut actors, &viewport), vec![player, coin]);
+        assert!(!world.spatial_index().contains(enemy));
+        assert!(world.spatial_index().contains(far));
+        assert_eq!(world.spatial_index().len(), 3);
+    }
+
+    #[test]
+    fn test_raycast() {
+        let mut world = World::new(Rect::new(0, 0, 400, 400), Broadphase::Quadtree);
+        let enemy = ActorData {
+            layers: CollisionLayers::new(0b10, 0),
+            ..tile(1, Rect::new(200, 20, 20, 20), TileShape::Solid)
+        };
+        let item = ActorData {
+            layers: CollisionLayers::new(0b100, 0),
+            ..tile(2, Rect::new(100, 20, 20, 20), TileShape::Solid)
+        };
+        world.spatial_index.insert(enemy);
+        world.spatial_index.insert(item);
+
+        // A wall of tiles in the third column
+        let mut tilemap = Tilemap::new(10, 10);
+        let wall = tilemap.add_tile_type(TileType {
+            sprite: None,
+            animation: None,
+            collision_filter: 0b1111,
+            shape: TileShape::Solid,
+            layers: CollisionLayers::new(0b1, 0),
+            actor_type: (),
+        });
+        for row in 0..10 {
+            tilemap.set(3, row, Some(wall));
+        }
+
+        let ray = Segment {
+            point: (10., 30.),
+            vector: Vector2D { x: 300., y: 0. },
+        };
+        let all = QueryFilter::new();
+
+        // The closest hit is the item and not the first actor in the index
+        let hit = world.raycast(&ray, &tilemap, &all).unwrap();
+        assert_eq!(hit.actor.index.id, 2);
+        assert_eq!(hit.point, (100., 30.));
+        assert_eq!(hit.normal, Vector2D { x: -1., y: 0. });
+        assert_eq!(hit.distance, 90.);
+
+        // Filtering out the item hits the wall and then the enemy
+        let hit = world.raycast(&ray, &tilemap, &all.mask(0b11)).unwrap();
+        assert!((hit.point.0 - f64::from(3 * GRID_SIZE)).abs() < 1e-9);
+        let hit = world.raycast(&ray, &tilemap, &all.mask(0b10)).unwrap();
+        assert_eq!(hit.actor.index.id, 1);
+
+        assert!(!world.line_of_sight((10., 30.), (300., 30.), &tilemap, &all));
+        assert!(world.line_of_sight((10., 30.), (300., 30.), &tilemap, &all.mask(0)));
+
+        // Rays that are too short or start inside the enemy hit nothing
+        let short = Segment {
+            point: (10., 30.),
+            vector: Vector2D { x: 50., y: 0. },
+        };
+        assert!(world.raycast(&short, &tilemap, &all).is_none());
+        let inside = Segment {
+            point: (210., 30.),
+            vector: Vector2D { x: 50., y: 0. },
+        };
+        assert!(world.raycast(&inside, &tilemap, &all).is_none());
+
+        // Triggers like coins don't block the line of sight
+        let coin = ActorData {
+            trigger: true,
+            ..tile(3, Rect::new(60, 20, 20, 20), TileShape::Solid)
+        };
+        world.spatial_index.insert(coin);
+        let hit = world.raycast(&ray, &tilemap, &all).unwrap();
+        assert_eq!(hit.actor.index.id, 2);
+        assert!(world.line_of_sight((10., 30.), (90., 30.), &tilemap, &all));
+    }
+
+    #[test]
+    fn test_raycast_shapes() {
+        // Rays only hit one-way platforms from above
+        let platform = tile(0, Rect::new(0, 40, 40, 40), TileShape::OneWay);
+        let down = Segment {
+            point: (20., 0.),
+            vector: Vector2D { x: 0., y: 100. },
+        };
+        let up = Segment {
+            point: (20., 100.),
+            vector: Vector2D { x: 0., y: -100. },
+        };
+        assert_eq!(cast_ray_actor(&down, &platform).unwrap().point, (20., 40.));
+        assert!(cast_ray_actor(&up, &platform).is_none());
+
+        // Rays hit the surface of slopes instead of their rectangle
+        let slope = tile(
+            0,
+            Rect::new(0, 40, 40, 40),
+            TileShape::Slope {
+                left: 0.0,
+                right: 1.0,
+            },
+        );
+        let hit = cast_ray_actor(&down, &slope).unwrap();
+        assert_eq!(hit.point, (20., 60.));
+        assert!(hit.normal.x < 0. && hit.normal.y < 0.);
+    }
+
+    #[test]
+    fn test_shape_cast() {
+        let mut world = World::new(Rect::new(0, 0, 400, 400), Broadphase::SpatialHash(40));
+        world
+            .spatial_index
+            .insert(tile(1, Rect::new(0, 0, 40, 20), TileShape::Solid));
+        world.spatial_index.insert(ActorData {
+            trigger: true,
+            ..tile(2, Rect::new(0, 40, 40, 20), TileShape::Solid)
+        });
+        let tilemap = Tilemap::new(0, 0);
+        let all = QueryFilter::new();
+
+        // A crouching actor under a block only has room to grow until the block
+        let crouching = BoundingBox::Rectangle(SpriteRectangle::new(5, 80, 30, 30));
+        let up = Vector2D { x: 0., y: -80. };
+        let hit = world.shape_cast(&crouching, &up, &tilemap, &all).unwrap();
+        assert_eq!(hit.actor.index.id, 1);
+        assert_eq!(hit.time, 0.75);
+        assert_eq!(hit.normal, Vector2D { x: 0., y: 1. });
+        assert!(
+            world
+                .shape_cast(&crouching, &Vector2D { x: 0., y: -50. }, &tilemap, &all)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_shape_cast_one_way() {
+        let platform = tile(0, Rect::new(0, 40, 40, 40), TileShape::OneWay);
+        let above = BoundingBox::Rectangle(SpriteRectangle::new(0, 0, 20, 20));
+        let below = BoundingBox::Rectangle(SpriteRectangle::new(0, 100, 20, 20));
+        let down = Vector2D { x: 0., y: 40. };
+        let up = Vector2D { x: 0., y: -40. };
+
+        assert_eq!(
+            cast_shape_actor(&above, &down, &platform).unwrap().time,
+            0.5
+        );
+        assert!(cast_shape_actor(&below, &up, &platform).is_none());
+    }
+}